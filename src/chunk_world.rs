@@ -0,0 +1,271 @@
+// the editor shows a window of this on the ground layer, see WorldView in main
+
+use std::collections::{HashMap, HashSet};
+
+use json::{object, JsonValue};
+
+use crate::tile_grid::*;
+
+// each chunk is a CHUNK_SIZE x CHUNK_SIZE TileGrid
+pub const CHUNK_SIZE: usize = 32;
+
+// (x, y) in world space, can go negative
+pub type WorldPos = (i64, i64);
+
+// a grid with no edges. chunks only get made when you paint into them,
+// so a 10,000 x 10,000 world with a house in the corner costs one chunk
+#[derive(Debug, Clone)]
+pub struct ChunkedWorld<T> {
+    chunks: HashMap<WorldPos, TileGrid<T>>,
+}
+
+// Returns (chunk position, position inside that chunk)
+pub fn world_to_chunk(pos: WorldPos) -> (WorldPos, (usize, usize)) {
+    let size = CHUNK_SIZE as i64;
+    let (x, y) = pos;
+    (
+        (x.div_euclid(size), y.div_euclid(size)),
+        (x.rem_euclid(size) as usize, y.rem_euclid(size) as usize),
+    )
+}
+
+pub fn chunk_to_world(chunk_pos: WorldPos, local: (usize, usize)) -> WorldPos {
+    let size = CHUNK_SIZE as i64;
+    (chunk_pos.0 * size + local.0 as i64, chunk_pos.1 * size + local.1 as i64)
+}
+
+// notes and turned cells count, a chunk is only empty if every cell is a default tile
+fn chunk_is_empty<T: PartialEq>(chunk: &TileGrid<T>) -> bool {
+    let empty = Tile::default();
+    (0..CHUNK_SIZE * CHUNK_SIZE).all(|i| *chunk.get_tile(index_to_pos(i, chunk.size())) == empty)
+}
+
+// can't derive it, that would make T need a default too
+impl<T> Default for ChunkedWorld<T> {
+    fn default() -> Self {
+        ChunkedWorld { chunks: HashMap::new() }
+    }
+}
+
+impl<T> ChunkedWorld<T>
+where
+    T: PartialEq,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // None if its chunk was never painted
+    pub fn get_tile(&self, pos: WorldPos) -> Option<&Tile<T>> {
+        let (chunk_pos, local) = world_to_chunk(pos);
+        Some(self.chunks.get(&chunk_pos)?.get_tile(local))
+    }
+
+    pub fn set_tile(&mut self, pos: WorldPos, tile: Tile<T>) {
+        let (chunk_pos, local) = world_to_chunk(pos);
+
+        // erasing somewhere that was never painted, don't make a chunk for it
+        if tile == Tile::default() && !self.chunks.contains_key(&chunk_pos) { return; }
+
+        self.chunk_mut(chunk_pos).set_tile(local, tile);
+    }
+
+    fn chunk_mut(&mut self, chunk_pos: WorldPos) -> &mut TileGrid<T> {
+        self.chunks
+            .entry(chunk_pos)
+            .or_insert_with(|| TileGrid::new(CHUNK_SIZE, CHUNK_SIZE))
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        return self.chunks.len();
+    }
+
+    // throw away chunks that have been erased back to nothing
+    pub fn remove_empty_chunks(&mut self) {
+        self.chunks.retain(|_, chunk| !chunk_is_empty(chunk));
+    }
+
+    // Returns (min, max) corners of the cells with something in them, inclusive
+    pub fn bounds(&self) -> Option<(WorldPos, WorldPos)> {
+        let mut result: Option<(WorldPos, WorldPos)> = None;
+        let empty = Tile::default();
+
+        for (chunk_pos, chunk) in self.chunks.iter() {
+            for i in 0..CHUNK_SIZE * CHUNK_SIZE {
+                if *chunk.get_tile(index_to_pos(i, chunk.size())) == empty { continue; }
+
+                let (x, y) = chunk_to_world(*chunk_pos, index_to_pos(i, chunk.size()));
+                result = Some(match result {
+                    None => ((x, y), (x, y)),
+                    Some((min, max)) => (
+                        (min.0.min(x), min.1.min(y)),
+                        (max.0.max(x), max.1.max(y)),
+                    ),
+                });
+            }
+        }
+
+        return result;
+    }
+}
+impl<T> ChunkedWorld<T>
+where
+    T: Clone + PartialEq,
+{
+    // cut a normal grid out of the world, origin is the top left corner. notes and orientations come too
+    pub fn to_grid(&self, origin: WorldPos, rows: usize, cols: usize) -> TileGrid<T> {
+        let mut grid = TileGrid::new(rows, cols);
+        for y in 0..rows {
+            for x in 0..cols {
+                let world_pos = (origin.0 + x as i64, origin.1 + y as i64);
+                if let Some(tile) = self.get_tile(world_pos) {
+                    grid.set_tile((x, y), tile.clone());
+                }
+            }
+        }
+        return grid;
+    }
+
+    // write a normal grid into the world, empty cells in the grid erase.
+    // chunks it erased back to nothing get thrown away, only the ones it touched get checked
+    pub fn paste_grid(&mut self, origin: WorldPos, grid: &TileGrid<T>) {
+        let mut touched = HashSet::new();
+        for y in 0..grid.rows {
+            for x in 0..grid.cols {
+                let world_pos = (origin.0 + x as i64, origin.1 + y as i64);
+                self.set_tile(world_pos, grid.get_tile((x, y)).clone());
+                touched.insert(world_to_chunk(world_pos).0);
+            }
+        }
+
+        for chunk_pos in touched {
+            if self.chunks.get(&chunk_pos).is_some_and(chunk_is_empty) {
+                self.chunks.remove(&chunk_pos);
+            }
+        }
+    }
+}
+
+impl<T> ToAndFromJsonValue for ChunkedWorld<T>
+where
+    T: ToAndFromJsonValue + PartialEq,
+{
+    fn to_json(&self) -> JsonValue {
+        let mut json_object = object! {
+            version: "1.0",
+            chunk_size: CHUNK_SIZE,
+            chunks: {},
+        };
+
+        // each chunk is a normal grid, keyed the same way tiles are
+        for ((cx, cy), chunk) in self.chunks.iter() {
+            if chunk_is_empty(chunk) { continue; }
+            json_object["chunks"][format!("({cx},{cy})")] = chunk.to_json();
+        }
+
+        return json_object;
+    }
+
+    // None for a world from something else, or one saved with a different chunk size
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        if source["version"] != "1.0" { return None; }
+        if source["chunk_size"].as_usize() != Some(CHUNK_SIZE) { return None; }
+
+        let mut new_world = ChunkedWorld::new();
+
+        for (key, value) in source["chunks"].entries() {
+            let chunk_pos = parse_pos_key(key)?;
+
+            let chunk: TileGrid<T> = TileGrid::from_json(value)?;
+            if chunk.size() != (CHUNK_SIZE, CHUNK_SIZE) { return None; }
+
+            new_world.chunks.insert(chunk_pos, chunk);
+        }
+
+        return Some(new_world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str) -> Tile<String> {
+        let mut grid = TileGrid::new(1, 1);
+        grid.set((0, 0), Some(name.to_owned()));
+        grid.get_tile((0, 0)).clone()
+    }
+
+    #[test]
+    fn negative_positions_land_in_their_own_chunks() {
+        assert_eq!(world_to_chunk((-1, -1)), ((-1, -1), (CHUNK_SIZE - 1, CHUNK_SIZE - 1)));
+        assert_eq!(world_to_chunk((CHUNK_SIZE as i64, 0)), ((1, 0), (0, 0)));
+        assert_eq!(chunk_to_world((-1, -1), (CHUNK_SIZE - 1, CHUNK_SIZE - 1)), (-1, -1));
+    }
+
+    #[test]
+    fn huge_sparse_world_only_makes_painted_chunks() {
+        let mut world = ChunkedWorld::new();
+        world.set_tile((0, 0), item("a"));
+        world.set_tile((9_999, 9_999), item("b"));
+        world.set_tile((-5_000, 42), item("c"));
+        // erasing somewhere that was never painted doesn't make anything
+        world.set_tile((5_000, 5_000), Tile::default());
+
+        assert_eq!(world.chunk_count(), 3);
+        assert_eq!(world.bounds(), Some(((-5_000, 0), (9_999, 9_999))));
+
+        let saved = world.to_json();
+        assert_eq!(saved["chunks"].len(), 3);
+        let loaded: ChunkedWorld<String> = ChunkedWorld::from_json(&saved).unwrap();
+        assert_eq!(loaded.get_tile((9_999, 9_999)), Some(&item("b")));
+    }
+
+    #[test]
+    fn windows_round_trip_and_drop_erased_chunks() {
+        let mut world = ChunkedWorld::new();
+        let mut window = TileGrid::new(4, 4);
+        window.set((1, 1), Some("a".to_owned()));
+        world.paste_grid((-2, -2), &window);
+        assert_eq!(world.chunk_count(), 1);
+        assert_eq!(world.to_grid((-2, -2), 4, 4).get((1, 1)).as_deref(), Some("a"));
+
+        world.paste_grid((-2, -2), &TileGrid::new(4, 4));
+        assert_eq!(world.chunk_count(), 0);
+    }
+
+    #[test]
+    fn notes_keep_a_chunk_alive() {
+        let mut window: TileGrid<String> = TileGrid::new(1, 1);
+        window.set_note((0, 0), Some("secret door".to_owned()));
+
+        let mut world = ChunkedWorld::new();
+        world.paste_grid((100, 100), &window);
+        world.remove_empty_chunks();
+        assert_eq!(world.chunk_count(), 1);
+        assert_eq!(world.to_grid((100, 100), 1, 1).get_note((0, 0)), Some("secret door"));
+    }
+
+    #[test]
+    fn foreign_or_broken_worlds_dont_load() {
+        let mut world = ChunkedWorld::new();
+        world.set_tile((0, 0), item("a"));
+        let good = world.to_json();
+
+        let mut broken = vec![json::parse(r#"{ "version": "2.0", "chunks": {} }"#).unwrap()];
+        let mut other_size = good.clone();
+        other_size["chunk_size"] = (CHUNK_SIZE * 2).into();
+        broken.push(other_size);
+        let mut bad_key = good.clone();
+        bad_key["chunks"]["nowhere"] = good["chunks"]["(0,0)"].clone();
+        broken.push(bad_key);
+        let mut small_chunk = good.clone();
+        small_chunk["chunks"]["(1,0)"] = TileGrid::<String>::new(2, 2).to_json();
+        broken.push(small_chunk);
+
+        for source in broken {
+            assert!(ChunkedWorld::<String>::from_json(&source).is_none(), "{source}");
+        }
+        assert!(ChunkedWorld::<String>::from_json(&good).is_some());
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::floor_stack::*;
+use crate::tile_grid::*;
+use crate::tile_map::*;
+use crate::region::*;
+use crate::map_object::*;
+
+// which grid an edit happened in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridTarget {
+    pub floor: usize,
+    pub layer: usize,
+}
+
+// every edit knows what it looked like before and after,
+// so it can be played backwards and forwards
+#[derive(Debug, Clone)]
+pub enum GridEdit<T> {
+    // one cell, item and note
+    Cell { target: GridTarget, pos: (usize, usize), before: Tile<T>, after: Tile<T> },
+    // the whole floor, for resizes. throwing cells away is the whole
+    // reason this exists, so keep all of them
+    Floor { floor: usize, before: TileMap<T>, after: TileMap<T> },
+    InsertFloor { index: usize, floor: TileMap<T> },
+    Links { before: Vec<FloorLink>, after: Vec<FloorLink> },
+    Regions { floor: usize, before: Vec<Region>, after: Vec<Region> },
+    Objects { floor: usize, before: Vec<MapObject>, after: Vec<MapObject> },
+}
+
+impl<T> GridEdit<T>
+where
+    T: Clone,
+{
+    fn undo(&self, floors: &mut FloorStack<T>) {
+        match self {
+            GridEdit::Cell { target, pos, before, .. } => {
+                floors.floor_mut(target.floor).layer_grid_mut(target.layer).set_tile(*pos, before.clone());
+            }
+            GridEdit::Floor { floor, before, .. } => replace_floor(floors, *floor, before),
+            GridEdit::InsertFloor { index, .. } => { floors.remove_floor(*index); }
+            GridEdit::Links { before, .. } => floors.links = before.clone(),
+            GridEdit::Regions { floor, before, .. } => floors.floor_mut(*floor).regions = before.clone(),
+            GridEdit::Objects { floor, before, .. } => floors.floor_mut(*floor).objects = before.clone(),
+        }
+    }
+
+    fn redo(&self, floors: &mut FloorStack<T>) {
+        match self {
+            GridEdit::Cell { target, pos, after, .. } => {
+                floors.floor_mut(target.floor).layer_grid_mut(target.layer).set_tile(*pos, after.clone());
+            }
+            GridEdit::Floor { floor, after, .. } => replace_floor(floors, *floor, after),
+            GridEdit::InsertFloor { index, floor } => floors.insert_floor(*index, floor.clone()),
+            GridEdit::Links { after, .. } => floors.links = after.clone(),
+            GridEdit::Regions { floor, after, .. } => floors.floor_mut(*floor).regions = after.clone(),
+            GridEdit::Objects { floor, after, .. } => floors.floor_mut(*floor).objects = after.clone(),
+        }
+    }
+}
+
+// undoing shouldn't yank you off the layer you're on
+fn replace_floor<T: Clone>(floors: &mut FloorStack<T>, index: usize, map: &TileMap<T>) {
+    let floor = floors.floor_mut(index);
+    let active_layer = floor.active_layer().name.clone();
+    *floor = map.clone();
+    floor.set_active_layer_by_name(&active_layer);
+}
+
+// a step is everything that gets undone by one ctrl+z, like a whole paint stroke
+pub struct EditHistory<T> {
+    undo_steps: VecDeque<Vec<GridEdit<T>>>,
+    redo_steps: Vec<Vec<GridEdit<T>>>,
+    open_step: Option<Vec<GridEdit<T>>>,
+
+    // oldest steps fall off the end after this many
+    pub max_depth: usize,
+}
+
+impl<T> EditHistory<T>
+where
+    T: Clone + PartialEq,
+{
+    pub fn new(max_depth: usize) -> Self {
+        EditHistory {
+            undo_steps: VecDeque::new(),
+            redo_steps: vec![],
+            open_step: None,
+            max_depth,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_steps.clear();
+        self.redo_steps.clear();
+        self.open_step = None;
+    }
+
+    // everything recorded until end_step gets undone together
+    pub fn begin_step(&mut self) {
+        if self.open_step.is_none() {
+            self.open_step = Some(vec![]);
+        }
+    }
+
+    pub fn end_step(&mut self) {
+        if let Some(step) = self.open_step.take() {
+            self.push_step(step);
+        }
+    }
+
+    fn push_step(&mut self, step: Vec<GridEdit<T>>) {
+        if step.is_empty() { return; }
+
+        self.undo_steps.push_back(step);
+        self.trim();
+        self.redo_steps.clear();
+    }
+
+    // max_depth can be turned down while there's history, so this runs on redo too
+    fn trim(&mut self) {
+        while self.undo_steps.len() > self.max_depth {
+            self.undo_steps.pop_front();
+        }
+    }
+
+    // these go together, joins the open step if there is one
+    pub fn record_step(&mut self, mut edits: Vec<GridEdit<T>>) {
+        if let Some(step) = self.open_step.as_mut() {
+            step.append(&mut edits);
+        } else {
+            self.push_step(edits);
+        }
+    }
+
+    // finds every cell that changed between before and after. it looks at the whole grid,
+    // so it's for one off edits like fills and pastes, the brush uses record_cell_changes
+    pub fn record_grid_changes(&mut self, target: GridTarget, before: &TileGrid<T>, after: &TileGrid<T>) {
+        assert!(before.size() == after.size(), "resizes go through GridEdit::Floor");
+
+        let mut edits = vec![];
+        for i in 0..before.rows * before.cols {
+            let pos = index_to_pos(i, before.size());
+            let (old_tile, new_tile) = (before.get_tile(pos), after.get_tile(pos));
+            if old_tile != new_tile {
+                edits.push(GridEdit::Cell { target, pos, before: old_tile.clone(), after: new_tile.clone() });
+            }
+        }
+
+        self.record_step(edits);
+    }
+
+    // before is what cells looked like before the edit, grid is after it.
+    // a map so a cell that's listed twice doesn't get recorded twice
+    pub fn record_cell_changes(&mut self, target: GridTarget, before: BTreeMap<(usize, usize), Tile<T>>, grid: &TileGrid<T>) {
+        let edits = before
+            .into_iter()
+            .filter(|(pos, old_tile)| grid.get_tile(*pos) != old_tile)
+            .map(|(pos, old_tile)| GridEdit::Cell { target, pos, before: old_tile, after: grid.get_tile(pos).clone() })
+            .collect();
+
+        self.record_step(edits);
+    }
+
+    // Returns false if there was nothing to undo
+    pub fn undo(&mut self, floors: &mut FloorStack<T>) -> bool {
+        self.end_step();
+
+        let Some(step) = self.undo_steps.pop_back() else { return false; };
+        for edit in step.iter().rev() {
+            edit.undo(floors);
+        }
+        self.redo_steps.push(step);
+
+        return true;
+    }
+
+    // Returns false if there was nothing to redo
+    pub fn redo(&mut self, floors: &mut FloorStack<T>) -> bool {
+        self.end_step();
+
+        let Some(step) = self.redo_steps.pop() else { return false; };
+        for edit in step.iter() {
+            edit.redo(floors);
+        }
+        self.undo_steps.push_back(step);
+        self.trim();
+
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floors() -> FloorStack<String> {
+        FloorStack::new(TileMap::new(1, 4, &["ground"]))
+    }
+
+    const TARGET: GridTarget = GridTarget { floor: 0, layer: 0 };
+
+    // paints x with item, recording just that cell
+    fn paint(history: &mut EditHistory<String>, floors: &mut FloorStack<String>, x: usize, item: &str) {
+        let grid = floors.floor_mut(0).layer_grid_mut(0);
+        let before = BTreeMap::from([((x, 0), grid.get_tile((x, 0)).clone())]);
+        grid.set((x, 0), Some(item.to_owned()));
+        history.record_cell_changes(TARGET, before, grid);
+    }
+
+    fn row(floors: &mut FloorStack<String>) -> Vec<Option<String>> {
+        let grid = floors.floor_mut(0).layer_grid_mut(0);
+        (0..4).map(|x| grid.get((x, 0)).clone()).collect()
+    }
+
+    #[test]
+    fn only_changed_cells_get_recorded() {
+        let (mut history, mut floors) = (EditHistory::new(10), floors());
+
+        history.begin_step();
+        paint(&mut history, &mut floors, 0, "a");
+        paint(&mut history, &mut floors, 0, "a");
+        paint(&mut history, &mut floors, 1, "b");
+        history.end_step();
+        assert_eq!(history.undo_steps.len(), 1);
+        assert_eq!(history.undo_steps[0].len(), 2);
+
+        // painting over the same thing isn't a step
+        paint(&mut history, &mut floors, 1, "b");
+        assert_eq!(history.undo_steps.len(), 1);
+
+        assert!(history.undo(&mut floors));
+        assert_eq!(row(&mut floors), vec![None; 4]);
+        assert!(history.redo(&mut floors));
+        assert_eq!(row(&mut floors), vec![Some("a".to_owned()), Some("b".to_owned()), None, None]);
+    }
+
+    #[test]
+    fn redo_keeps_to_max_depth() {
+        let (mut history, mut floors) = (EditHistory::new(3), floors());
+        for x in 0..3 { paint(&mut history, &mut floors, x, "a"); }
+
+        assert!(history.undo(&mut floors));
+        assert!(history.undo(&mut floors));
+        history.max_depth = 1;
+        assert!(history.redo(&mut floors));
+        assert!(history.redo(&mut floors));
+        assert_eq!(history.undo_steps.len(), 1);
+
+        // only the last one is left to undo
+        assert!(history.undo(&mut floors));
+        assert!(!history.undo(&mut floors));
+        assert_eq!(row(&mut floors), vec![Some("a".to_owned()), Some("a".to_owned()), None, None]);
+    }
+}
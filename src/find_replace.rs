@@ -0,0 +1,182 @@
+// swapping one tile for another in every map file in a folder, for when a sprite
+// gets renamed or replaced. works on plain grids, single floor maps and floor stacks
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use json::JsonValue;
+
+use crate::tile_grid::*;
+use crate::tile_definition::*;
+use crate::map_merge::{grids_in_map, is_map_json};
+
+// only_layer limits it to layers with that name, plain grids don't have a name so they get skipped.
+// Returns the number of cells changed, or None if one of the grids is broken
+pub fn replace_in_map_json(source: &mut JsonValue, find: &str, replace: &str, only_layer: Option<&str>) -> Option<usize> {
+    if source.has_key("floors") {
+        return source["floors"]
+            .members_mut()
+            .map(|floor| replace_in_map_json(floor, find, replace, only_layer))
+            .sum();
+    }
+
+    if source.has_key("layers") {
+        return source["layers"]
+            .members_mut()
+            .filter(|layer| only_layer.is_none_or(|name| layer["name"] == name))
+            .map(|layer| replace_in_grid_json(layer, find, replace))
+            .sum();
+    }
+
+    if only_layer.is_some() { return Some(0); }
+    return replace_in_grid_json(source, find, replace);
+}
+
+// layers have a name next to the grid, so only the tiles get written back
+fn replace_in_grid_json(source: &mut JsonValue, find: &str, replace: &str) -> Option<usize> {
+    let mut grid: TileGrid<String> = TileGrid::from_json(source)?;
+
+    let changed = grid.replace_all(&find.to_owned(), &replace.to_owned(), None);
+    if changed > 0 {
+        source["tiles"] = grid.to_json()["tiles"].take();
+    }
+    return Some(changed);
+}
+
+// the saved palette should match what's in the map. the new tile's definition
+// comes from the manifest next to it, if there is one
+fn fix_palette(source: &mut JsonValue, find: &str, replace: &str) {
+    if !source.has_key("palette") { return; }
+
+    // a replace on one layer can leave it on the others
+    let still_used = grids_in_map(source).unwrap_or_default().iter().any(|(_, grid)| grid.items().any(|item| item == find));
+    if !still_used {
+        source["palette"].remove(find);
+    }
+
+    if source["palette"].has_key(replace) { return; }
+    let Some(dir) = Path::new(replace).parent() else { return; };
+    if let Some((_, definition)) = load_palette_manifest(dir).0.into_iter().find(|(name, _)| name == replace) {
+        source["palette"][replace] = definition.to_json();
+    }
+}
+
+// `tile_editor replace <dir> <find> <replace> [layer]`, every map in dir gets written over.
+// every file gets read and checked first, if any of them is broken nothing gets written,
+// so a folder never ends up half replaced.
+// Returns the exit code, 1 if anything went wrong. nothing being there to replace is fine
+pub fn run_replace(dir: &str, find: &str, replace: &str, only_layer: Option<&str>) -> i32 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        eprintln!("{dir}: can't read the folder");
+        return 1;
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    paths.sort();
+
+    let mut replaced = vec![];
+    let mut errors = vec![];
+    for path in paths {
+        let mut buffer = String::new();
+        if fs::File::open(&path).and_then(|mut input| input.read_to_string(&mut buffer)).is_err() {
+            errors.push(format!("{}: can't read it", path.display()));
+            continue;
+        }
+
+        let Ok(mut source) = json::parse(&buffer) else {
+            errors.push(format!("{}: not json", path.display()));
+            continue;
+        };
+        if !is_map_json(&source) { continue; }
+
+        let Some(changed) = replace_in_map_json(&mut source, find, replace, only_layer) else {
+            errors.push(format!("{}: has a broken grid", path.display()));
+            continue;
+        };
+        if changed > 0 {
+            fix_palette(&mut source, find, replace);
+        }
+        replaced.push((path, source, changed));
+    }
+
+    if !errors.is_empty() {
+        for error in errors { eprintln!("{error}"); }
+        eprintln!("nothing was changed, fix those first");
+        return 1;
+    }
+
+    let mut total = 0;
+    let mut failed = false;
+    for (path, source, changed) in replaced {
+        println!("{}: {changed} cells", path.display());
+        total += changed;
+
+        if changed == 0 { continue; }
+        let written = fs::File::create(&path).and_then(|mut output| write!(output, "{}", source));
+        if written.is_err() {
+            eprintln!("{}: couldn't write it", path.display());
+            failed = true;
+        }
+    }
+
+    println!("{total} cells changed");
+    return if failed { 1 } else { 0 };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("tile_editor_replace_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        return dir;
+    }
+
+    fn map_with(tile: &str) -> String {
+        let mut grid: TileGrid<String> = TileGrid::new(1, 2);
+        grid.set((0, 0), Some(tile.to_owned()));
+        return grid.to_json().dump();
+    }
+
+    #[test]
+    fn replaces_in_every_map() {
+        let dir = temp_dir("ok");
+        fs::write(dir.join("a.json"), map_with("grass")).unwrap();
+        fs::write(dir.join("b.json"), map_with("dirt")).unwrap();
+        fs::write(dir.join(PALETTE_MANIFEST_FILE), r#"{ "version": "1.0", "tiles": {} }"#).unwrap();
+
+        assert_eq!(run_replace(dir.to_str().unwrap(), "grass", "moss", None), 0);
+        let a = fs::read_to_string(dir.join("a.json")).unwrap();
+        let b = fs::read_to_string(dir.join("b.json")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(a, map_with("moss"));
+        assert_eq!(b, map_with("dirt"));
+    }
+
+    #[test]
+    fn one_broken_map_stops_all_of_them() {
+        for broken in ["{ oops", r#"{ "version": "1.0", "rows": 1, "cols": 1, "tiles": { "9,9": "grass" } }"#] {
+            let dir = temp_dir("broken");
+            fs::write(dir.join("a.json"), map_with("grass")).unwrap();
+            fs::write(dir.join("b.json"), broken).unwrap();
+
+            assert_eq!(run_replace(dir.to_str().unwrap(), "grass", "moss", None), 1);
+            let a = fs::read_to_string(dir.join("a.json")).unwrap();
+            fs::remove_dir_all(&dir).unwrap();
+
+            assert_eq!(a, map_with("grass"));
+        }
+    }
+
+    #[test]
+    fn missing_folder_fails() {
+        assert_eq!(run_replace("/this/folder/isnt/there", "grass", "moss", None), 1);
+    }
+}
@@ -0,0 +1,316 @@
+use json::{object, JsonValue};
+
+use crate::tile_grid::*;
+use crate::tile_map::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkEnd {
+    pub floor: usize,
+    pub pos: (usize, usize),
+}
+
+// stairs, ladders, ect. a named way to get from one floor to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FloorLink {
+    pub name: String,
+    pub from: LinkEnd,
+    pub to: LinkEnd,
+}
+
+// a dungeon, one TileMap per floor, all using the same pallet
+#[derive(Debug, Clone)]
+pub struct FloorStack<T> {
+    floors: Vec<TileMap<T>>,
+    active_floor: usize,
+    pub links: Vec<FloorLink>,
+}
+
+impl<T> FloorStack<T> {
+    pub fn new(first_floor: TileMap<T>) -> Self {
+        FloorStack {
+            floors: vec![first_floor],
+            active_floor: 0,
+            links: vec![],
+        }
+    }
+
+    pub fn floors(&self) -> &[TileMap<T>] {
+        return &self.floors;
+    }
+
+    pub fn active_floor_index(&self) -> usize {
+        return self.active_floor;
+    }
+
+    pub fn active_floor(&self) -> &TileMap<T> {
+        return &self.floors[self.active_floor];
+    }
+    pub fn active_floor_mut(&mut self) -> &mut TileMap<T> {
+        return &mut self.floors[self.active_floor];
+    }
+
+    pub fn floor_mut(&mut self, index: usize) -> &mut TileMap<T> {
+        return &mut self.floors[index];
+    }
+
+    // doesn't wrap around, there is no floor above the roof
+    pub fn change_floor(&mut self, count: i32) {
+        let new_floor = self.active_floor as i32 + count;
+        self.active_floor = new_floor.clamp(0, self.floors.len() as i32 - 1) as usize;
+    }
+
+    // puts the new floor at index, and moves to it
+    pub fn insert_floor(&mut self, index: usize, floor: TileMap<T>) {
+        self.floors.insert(index, floor);
+
+        for link in self.links.iter_mut() {
+            for end in [&mut link.from, &mut link.to] {
+                if end.floor >= index { end.floor += 1; }
+            }
+        }
+
+        self.active_floor = index;
+    }
+
+    // links that touch the floor go with it
+    pub fn remove_floor(&mut self, index: usize) -> TileMap<T> {
+        assert!(self.floors.len() > 1, "can't remove the last floor");
+        let floor = self.floors.remove(index);
+
+        self.links.retain(|link| link.from.floor != index && link.to.floor != index);
+        for link in self.links.iter_mut() {
+            for end in [&mut link.from, &mut link.to] {
+                if end.floor > index { end.floor -= 1; }
+            }
+        }
+
+        self.active_floor = self.active_floor.min(self.floors.len() - 1);
+        return floor;
+    }
+
+    pub fn add_link(&mut self, name: &str, from: LinkEnd, to: LinkEnd) {
+        self.links.push(FloorLink { name: name.to_owned(), from, to });
+    }
+
+    // the first "link N" nobody has, counting the len doesn't work once links get deleted or undone
+    pub fn unused_link_name(&self) -> String {
+        let mut number = 1;
+        loop {
+            let name = format!("link {number}");
+            if !self.link_name_taken(&name) { return name; }
+            number += 1;
+        }
+    }
+
+    pub fn link_name_taken(&self, name: &str) -> bool {
+        self.links.iter().any(|link| link.name == name)
+    }
+
+    // Returns the links that have an end on the floor, and the end thats on it
+    pub fn links_on_floor(&self, floor: usize) -> Vec<(&FloorLink, LinkEnd)> {
+        let mut result = vec![];
+        for link in self.links.iter() {
+            if link.from.floor == floor { result.push((link, link.from)); }
+            if link.to  .floor == floor { result.push((link, link.to  )); }
+        }
+        return result;
+    }
+
+    pub fn end_exists(&self, end: LinkEnd) -> bool {
+        let Some(floor) = self.floors.get(end.floor) else { return false; };
+        let (x, y) = end.pos;
+        return x < floor.cols && y < floor.rows;
+    }
+
+    // Returns a message for every link end that doesn't point at a real cell
+    pub fn validate_links(&self) -> Vec<String> {
+        let mut errors = vec![];
+        for link in self.links.iter() {
+            for (which, end) in [("from", link.from), ("to", link.to)] {
+                if !self.end_exists(end) {
+                    errors.push(format!(
+                        "link '{}' {which} end (floor {}, {:?}) doesn't exist",
+                        link.name, end.floor, end.pos
+                    ));
+                }
+            }
+        }
+        return errors;
+    }
+}
+impl<T> FloorStack<T>
+where
+    T: Clone,
+{
+    // link ends on the floor move with their cells,
+    // links that lose an end off the edge get removed
+    pub fn resize_active_floor(&mut self, new_rows: usize, new_cols: usize, anchor: Anchor) {
+        let floor = self.active_floor;
+        let old_size = self.active_floor().size();
+        let edges = EdgeDeltas::anchored(old_size, (new_rows, new_cols), anchor);
+
+        self.active_floor_mut().resize_edges(edges);
+
+        self.links.retain_mut(|link| {
+            for end in [&mut link.from, &mut link.to] {
+                if end.floor != floor { continue; }
+                let Some(pos) = edges.move_pos(end.pos, old_size) else { return false; };
+                end.pos = pos;
+            }
+            return true;
+        });
+    }
+
+    // link ends on the floor turn with it
+    pub fn transform_active_floor(&mut self, transform: GridTransform) {
+        let floor = self.active_floor;
+        let old_size = self.active_floor().size();
+
+        self.active_floor_mut().transform(transform);
+
+        for link in self.links.iter_mut() {
+            for end in [&mut link.from, &mut link.to] {
+                if end.floor == floor { end.pos = transform.move_pos(end.pos, old_size); }
+            }
+        }
+    }
+}
+
+impl ToAndFromJsonValue for LinkEnd {
+    fn to_json(&self) -> JsonValue {
+        object! {
+            floor: self.floor,
+            x: self.pos.0,
+            y: self.pos.1,
+        }
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        Some(LinkEnd {
+            floor: source["floor"].as_usize()?,
+            pos: (source["x"].as_usize()?, source["y"].as_usize()?),
+        })
+    }
+}
+
+impl ToAndFromJsonValue for FloorLink {
+    fn to_json(&self) -> JsonValue {
+        object! {
+            name: self.name.clone(),
+            from: self.from.to_json(),
+            to: self.to.to_json(),
+        }
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        Some(FloorLink {
+            name: source["name"].as_str()?.to_owned(),
+            from: LinkEnd::from_json(&source["from"])?,
+            to: LinkEnd::from_json(&source["to"])?,
+        })
+    }
+}
+
+impl<T> ToAndFromJsonValue for FloorStack<T>
+where
+    T: ToAndFromJsonValue,
+{
+    fn to_json(&self) -> JsonValue {
+        let mut json_object = object! {
+            version: "1.0",
+            active_floor: self.active_floor,
+            floors: [],
+            links: [],
+        };
+
+        for floor in self.floors.iter() {
+            json_object["floors"].push(floor.to_json()).expect("floors is an array");
+        }
+        for link in self.links.iter() {
+            json_object["links"].push(link.to_json()).expect("links is an array");
+        }
+
+        return json_object;
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        // older saves are a single floor
+        if source["floors"].is_null() {
+            return Some(FloorStack::new(TileMap::from_json(source)?));
+        }
+
+        if source["version"] != "1.0" { return None; }
+
+        let floors: Vec<TileMap<T>> = source["floors"]
+            .members()
+            .map(TileMap::from_json)
+            .collect::<Option<_>>()?;
+
+        if floors.is_empty() { return None; }
+
+        let links = source["links"]
+            .members()
+            .map(FloorLink::from_json)
+            .collect::<Option<_>>()?;
+
+        let active_floor = source["active_floor"].as_usize().unwrap_or(0).min(floors.len() - 1);
+
+        let new_stack = FloorStack { floors, active_floor, links };
+
+        let errors = new_stack.validate_links();
+        if !errors.is_empty() {
+            for error in errors { println!("Bad floor link: {error}"); }
+            return None;
+        }
+
+        return Some(new_stack);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn end(floor: usize) -> LinkEnd {
+        LinkEnd { floor, pos: (0, 0) }
+    }
+
+    #[test]
+    fn link_names_dont_repeat() {
+        let mut floors: FloorStack<String> = FloorStack::new(TileMap::new(2, 2, &["ground"]));
+        assert_eq!(floors.unused_link_name(), "link 1");
+
+        floors.add_link(&floors.unused_link_name(), end(0), end(0));
+        floors.add_link(&floors.unused_link_name(), end(0), end(0));
+        floors.add_link("stairs", end(0), end(0));
+        assert_eq!(floors.links.iter().map(|link| link.name.as_str()).collect::<Vec<_>>(), ["link 1", "link 2", "stairs"]);
+
+        // taking one out (or undoing it) frees its name, without clashing with the ones after it
+        floors.links.remove(0);
+        assert_eq!(floors.unused_link_name(), "link 1");
+        floors.add_link(&floors.unused_link_name(), end(0), end(0));
+        assert_eq!(floors.unused_link_name(), "link 3");
+        assert!(floors.link_name_taken("stairs"));
+    }
+
+    #[test]
+    fn broken_stacks_dont_load() {
+        let mut floors: FloorStack<String> = FloorStack::new(TileMap::new(2, 2, &["ground"]));
+        floors.add_link("stairs", end(0), end(0));
+        let good = floors.to_json();
+        assert!(FloorStack::<String>::from_json(&good).is_some());
+
+        let mut wrong_version = good.clone();
+        wrong_version["version"] = "0.1".into();
+        let mut bad_floor = good.clone();
+        bad_floor["floors"][0]["rows"] = "two".into();
+        let mut bad_link = good.clone();
+        bad_link["links"][0].remove("from");
+        let mut link_off_the_map = good.clone();
+        link_off_the_map["links"][0]["to"]["floor"] = 5.into();
+
+        for source in [wrong_version, bad_floor, bad_link, link_off_the_map] {
+            assert!(FloorStack::<String>::from_json(&source).is_none(), "{source}");
+        }
+    }
+}
@@ -1,7 +1,14 @@
+use std::collections::HashMap;
+
+use crate::tile_definition::TileDefinition;
+
 pub struct MyIconServer<T> {
     pub assets: Vec<(String, T)>, // TODO: Remove pub at some point? make hidden ect...
     selected: String,     // TODO: use str
     default_icon: String, // TODO: use str
+
+    // description, tags and properties, keyed by icon name
+    definitions: HashMap<String, TileDefinition>,
 }
 
 impl<T> MyIconServer<T> {
@@ -11,6 +18,7 @@ impl<T> MyIconServer<T> {
 			default_icon: assets[0].0.clone(),
 			selected: assets[1].0.clone(),
 			assets,
+			definitions: HashMap::new(),
 		}
 	}
 
@@ -37,6 +45,15 @@ impl<T> MyIconServer<T> {
             .map(|(_, handle)| handle)
     }
 
+    pub fn get_definition(&self, name: &str) -> Option<&TileDefinition> {
+        self.definitions.get(name)
+    }
+
+    // doesn't need the icon to be loaded yet, manifests can come first
+    pub fn set_definition(&mut self, name: &str, definition: TileDefinition) {
+        self.definitions.insert(name.to_owned(), definition);
+    }
+
     pub fn get_default_handle(&self) -> &T {
         self.get_by_name(&self.default_icon)
             .expect("self.default_icon is valid")
@@ -1,18 +1,60 @@
 mod mouse_context;
 mod tile_grid;
+mod tile_map;
+mod floor_stack;
+mod chunk_world;
 mod icon_server;
+mod tile_definition;
+mod edit_history;
+mod shapes;
+mod stamp_library;
+mod terrain;
+mod topology;
+mod projection;
+mod region;
+mod map_object;
+mod map_merge;
+mod find_replace;
+mod tile_stats;
+mod walkability;
+mod map_lint;
+mod random;
+mod wfc;
+mod procgen;
 mod panel_ui;
 mod file_dialog;
 
 use mouse_context::*;
 use tile_grid::*;
+use tile_map::*;
+use floor_stack::*;
+use chunk_world::*;
 use icon_server::*;
+use tile_definition::*;
+use edit_history::*;
+use shapes::*;
+use stamp_library::*;
+use terrain::*;
+use topology::*;
+use projection::*;
+use region::*;
+use map_object::*;
+use map_merge::*;
+use find_replace::*;
+use tile_stats::*;
+use walkability::*;
+use map_lint::*;
+use random::*;
+use wfc::*;
+use procgen::*;
 use panel_ui::*;
 use file_dialog::*;
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::io::{Read, Write};
 use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use raylib::prelude::*;
 
@@ -24,9 +66,20 @@ const SQUARE_SIZE_F     : f32 = SQUARE_SIZE as f32;
 const SQUARE_SPACING    : i32 = 10;
 
 const QUICK_SAVE_FILE : &str = "quick-save.json";
+const WORLD_FILE      : &str = "world.json";
+const STATS_CSV_FILE  : &str = "tile-stats.csv";
+const STATS_JSON_FILE : &str = "tile-stats.json";
+// checked on every save, if it's there
+const LINT_RULES_FILE : &str = "lint-rules.json";
+// any more and the lint panel runs off the window, the rest are summed up in one line
+const LINT_PANEL_MAX_LINES : usize = 8;
+
+// how many ctrl+z's you get
+const HISTORY_DEPTH : usize = 100;
 
 // TODO: Remove hardcode? is it good to have something in the pallet at startup?
 const PATH            : &str = "./assets/icons";
+const STAMP_PATH      : &str = "./assets/stamps";
 
 const TEXT_SIZE    : i32 = 20;
 const TEXT_PADDING : i32 = 10;
@@ -37,6 +90,45 @@ const HIGHLIGHT_COLOR       : Color = Color::ORANGE;
 const PALLET_SELECTED_COLOR : Color = Color::RED;
 const PALLET_DEFAULT_COLOR  : Color = Color::BLUE;
 
+const LINK_MARKER_COLOR     : Color = Color::PURPLE;
+const LINK_PENDING_COLOR    : Color = Color::MAGENTA;
+
+const NOTE_MARKER_COLOR     : Color = Color::YELLOW;
+const NOTE_BACKGROUND_COLOR : Color = Color::DARKGRAY;
+const NOTE_TEXT_COLOR       : Color = Color::GOLD;
+
+const SHAPE_PREVIEW_COLOR   : Color = Color::GREEN;
+const SHAPE_ERASE_COLOR     : Color = Color::MAROON;
+const SELECTION_COLOR       : Color = Color::SKYBLUE;
+const FLOATING_COLOR        : Color = Color::LIME;
+
+// new regions go through these
+const REGION_COLORS     : [Color; 6] = [Color::RED, Color::BLUE, Color::GREEN, Color::PURPLE, Color::ORANGE, Color::SKYBLUE];
+const REGION_FILL_ALPHA : u8 = 60;
+
+// objects are drawn smaller than a cell, so you can see what they're on
+const OBJECT_SIZE           : f32   = 32.0;
+const OBJECT_SELECTED_COLOR : Color = Color::YELLOW;
+const OBJECT_TEXT_COLOR     : Color = Color::BLACK;
+
+// the biggest walkable area isn't marked, only the bits you can't get to from it
+const WALK_CUT_OFF_COLOR    : Color = Color::PINK;
+const WALK_PATH_COLOR       : Color = Color::DARKGREEN;
+const WALK_UNREACHABLE_COLOR: Color = Color::RED;
+const LINT_VIOLATION_COLOR  : Color = Color::ORANGE;
+
+const PROPOSAL_COLOR        : Color = Color::VIOLET;
+const CONTRADICTION_COLOR   : Color = Color::RED;
+
+const STAMP_PANEL_BACKGROUND_COLOR : Color = Color::DARKGRAY;
+const STAMP_PANEL_TEXT_COLOR       : Color = Color::GOLD;
+const STAMP_PANEL_HOVER_COLOR      : Color = Color::ORANGE;
+const STAMP_PANEL_MISSING_COLOR    : Color = Color::RED;
+
+const INSPECTOR_ITEM_PADDING     : i32   = 4;
+const INSPECTOR_BACKGROUND_COLOR : Color = Color::DARKBLUE;
+const INSPECTOR_TEXT_COLOR       : Color = Color::RAYWHITE;
+
 const GRID_START_POSITION   : Vector2 = Vector2::new(100.0, 100.0);
 const PALLET_START_POSITION : Vector2 = Vector2::new(10.0, 10.0);
 
@@ -48,17 +140,240 @@ pub struct ImageContainer {
 // these thing have to go together, so why not make it official?
 struct GridHandler {
     icon_server: MyIconServer<ImageContainer>,
+    floors: FloorStack<String>,
+
+    // first end of a link thats waiting for its other end
+    pending_link: Option<LinkEnd>,
+
+    note_editor: Option<NoteEditor>,
+
+    history: EditHistory<String>,
+    // a stroke only paints if it started on the grid
+    painting: bool,
+
+    tool: EditorTool,
+    // how painted tiles face
+    brush_orientation: Orientation,
+    terrains: Vec<TerrainSet>,
+    shape_drag: Option<ShapeDrag>,
+
+    // two corners, in the order they were dragged
+    selection: Option<((usize, usize), (usize, usize))>,
+    selecting: bool,
+    clipboard: Option<TileGrid<String>>,
+    floating: Option<FloatingGrid>,
+
+    stamps: Vec<Stamp<String>>,
+    stamp_panel_open: bool,
+    stats_panel_open: bool,
+    walk_check_open: bool,
+    // and the floor it was worked out for, it only gets checked again when the floor changes
+    walk_report: Option<(TileMap<String>, WalkReport)>,
+    // from the last save, None once the panel is closed
+    lint_violations: Option<Vec<LintViolation>>,
+    proposal: Option<Proposal>,
+    world: Option<WorldView>,
+    // the name being typed for the selection, before it's saved as a stamp
+    stamp_name_editor: Option<String>,
+    region_drag: Option<RegionDrag>,
+    object_drag: Option<ObjectDrag>,
+    // (floor, index) of the object the object tool is working on
+    selected_object: Option<(usize, usize)>,
+    // the name being typed for a region or object that was just made
+    name_editor: Option<(NameTarget, String)>,
+}
+
+// what clicking on the grid does
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EditorTool {
+    Brush,
+    Fill(Connectivity),
+    Shape(Shape),
+    Select,
+    // paints terrains[index]
+    Terrain(usize),
+    Region,
+    Object,
+}
+
+impl EditorTool {
+    fn name(&self) -> String {
+        match self {
+            EditorTool::Brush => "Brush".to_owned(),
+            EditorTool::Fill(Connectivity::Four ) => "Fill (4)".to_owned(),
+            EditorTool::Fill(Connectivity::Eight) => "Fill (8)".to_owned(),
+            EditorTool::Fill(Connectivity::Hex(_)) => "Fill (hex)".to_owned(),
+            EditorTool::Shape(shape) => shape.name().to_owned(),
+            EditorTool::Select => "Select".to_owned(),
+            EditorTool::Terrain(_) => "Terrain".to_owned(),
+            EditorTool::Region => "Region".to_owned(),
+            EditorTool::Object => "Object".to_owned(),
+        }
+    }
+}
+
+// a shape being dragged out, it only goes in the grid when the button comes up
+struct ShapeDrag {
+    shape: Shape,
+    start: (usize, usize),
+    end: (usize, usize),
+    // started with the right button
+    erase: bool,
+}
+
+// a region being made or having one of its corners moved
+struct RegionDrag {
+    floor: usize,
+    index: usize,
+    // the corner that isn't moving
+    fixed: (usize, usize),
+    // the regions before the drag, so the whole thing is one undo
+    before: Vec<Region>,
+    // just made, it gets a name when the drag is done
+    is_new: bool,
+}
+
+// an object on the active floor following the mouse
+struct ObjectDrag {
+    floor: usize,
+    index: usize,
+    // the objects before the drag, so the whole thing is one undo
+    before: Vec<MapObject>,
+}
+
+// what a name_editor is naming, by index on the active floor (links are for the whole stack)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NameTarget {
+    Region(usize),
+    Object(usize),
+    Link(usize),
+}
+
+// the extra item (and how it faces) drawn on a cell, and its highlight
+type GridOverlay<'a> = BTreeMap<(usize, usize), (Option<(&'a str, Orientation)>, Color)>;
+
+// a pasted or moved piece of grid that follows the mouse until it's dropped
+struct FloatingGrid {
     grid: TileGrid<String>,
+    // the grid it's floating over, it gets put away if you switch floor or layer
+    target: GridTarget,
+    // top left corner, on the active grid
+    origin: (usize, usize),
+    // which of its cells the mouse is holding
+    grab: (usize, usize),
+    // where a move picked it up from, cancelling puts it back there
+    home: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Generator {
+    // learns from the stamps and the layer
+    Wfc,
+    Caves(CaveSettings),
+    Dungeon(DungeonSettings),
+}
+
+impl Generator {
+    // keeps the settings of the one it's moving off, they're only tweaked in the panel
+    fn next(&self) -> Generator {
+        match self {
+            Generator::Wfc        => Generator::Caves(CaveSettings::default()),
+            Generator::Caves(_)   => Generator::Dungeon(DungeonSettings::default()),
+            Generator::Dungeon(_) => Generator::Wfc,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Generator::Wfc        => "wave function collapse",
+            Generator::Caves(_)   => "caves",
+            Generator::Dungeon(_) => "dungeon",
+        }
+    }
+}
+
+// a generated fill that's only drawn over the grid until it's accepted
+struct Proposal {
+    target: GridTarget,
+    // corners, inclusive
+    area: ((usize, usize), (usize, usize)),
+    generator: Generator,
+    // what caves and dungeons are drawn with, None leaves the cells empty
+    wall: Option<String>,
+    floor: Option<String>,
+    // keep what's already there and only fill the gaps
+    only_empty: bool,
+    seed: u64,
+    result: Result<TileGrid<String>, WfcContradiction>,
+}
+
+// the ground layer of one floor shows a window of the world. edits land in the
+// layer like normal and get written back when the window moves or the world is saved
+struct WorldView {
+    world: ChunkedWorld<String>,
+    // the world cell in the top left of the window
+    origin: WorldPos,
+    floor: usize,
+}
+
+// the note being typed into a cell on the active layer
+struct NoteEditor {
+    pos: (usize, usize),
+    text: String,
 }
 
 
 fn main() {
+    // command line tools for map files, no window
+    let args: Vec<String> = std::env::args().collect();
+    match args.iter().skip(1).map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["diff", old, new] => std::process::exit(run_diff(old, new)),
+        ["merge", base, ours, theirs] => std::process::exit(run_merge(base, ours, theirs)),
+        ["replace", dir, find, replace] => std::process::exit(run_replace(dir, find, replace, None)),
+        ["replace", dir, find, replace, layer] => std::process::exit(run_replace(dir, find, replace, Some(layer))),
+        ["validate", paths @ ..] if !paths.is_empty() => std::process::exit(run_validate(paths)),
+        ["lint", "--json", rules, paths @ ..] if !paths.is_empty() => std::process::exit(run_lint(rules, paths, true)),
+        ["lint", rules, paths @ ..] if !paths.is_empty() => std::process::exit(run_lint(rules, paths, false)),
+        [] => {}
+        _ => {
+            println!("usage: tile_editor [diff <old> <new> | merge <base> <ours> <theirs> | replace <dir> <find> <replace> [layer] | validate <map>... | lint [--json] <rules> <map>...]");
+            std::process::exit(2);
+        }
+    }
+
     let assets = get_images_from_path(Path::new(PATH));
 
     let mut grid_handler = GridHandler {
         icon_server: MyIconServer::new(assets),
-        grid: TileGrid::new(4, 6),
+        floors: FloorStack::new(TileMap::new(4, 6, &DEFAULT_LAYER_NAMES)),
+        pending_link: None,
+        note_editor: None,
+        history: EditHistory::new(HISTORY_DEPTH),
+        painting: false,
+        tool: EditorTool::Brush,
+        brush_orientation: Orientation::default(),
+        terrains: vec![],
+        shape_drag: None,
+        selection: None,
+        selecting: false,
+        clipboard: None,
+        floating: None,
+        stamps: vec![],
+        stamp_panel_open: false,
+        stats_panel_open: false,
+        walk_check_open: false,
+        walk_report: None,
+        lint_violations: None,
+        proposal: None,
+        world: None,
+        stamp_name_editor: None,
+        region_drag: None,
+        object_drag: None,
+        selected_object: None,
+        name_editor: None,
     };
+    grid_handler.load_definitions(Path::new(PATH));
+    grid_handler.load_stamps();
 
     let (mut rl, thread) = raylib::init()
         .size(WINDOW_WIDTH, WINDOW_HEIGHT)
@@ -78,54 +393,200 @@ fn main() {
     while !rl.window_should_close() {
 
         /* -------------------- KEY EVENT HANDLERS -------------------- */
-    
-        { // Quick (Save / Load) Handler
-            if rl.is_key_pressed(KeyboardKey::KEY_P) {
-                println!("Saving Grid!"); // TODO: draw something to the screen
 
-                let json_string = grid_handler.grid.to_json().to_string();
-                let mut output = fs::File::create(QUICK_SAVE_FILE).expect("File was created");
-                write!(output, "{}", json_string).expect("Write to file");
+        // while a note or name is being typed, the keyboard belongs to it
+        let typing = grid_handler.note_editor.is_some()
+            || grid_handler.stamp_name_editor.is_some()
+            || grid_handler.name_editor.is_some();
+        if typing {
+            grid_handler.update_note_editor(&mut rl);
+            grid_handler.update_stamp_name_editor(&mut rl);
+            grid_handler.update_name_editor(&mut rl);
+        }
+
+        if !typing {
+            let ctrl_down = rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+
+            { // Undo / Redo
+                if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_Z) { grid_handler.undo() }
+                if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_Y) { grid_handler.redo() }
             }
-            if rl.is_key_pressed(KeyboardKey::KEY_L) {
-                println!("Loading Saved Grid!"); // TODO: draw something to the screen
 
-                if let Ok(mut input) = fs::File::open(QUICK_SAVE_FILE) {
-                    let mut buffer = String::new();
-                    input.read_to_string(&mut buffer).expect("Read to buffer");
-        
-                    grid_handler.grid = TileGrid::from_json(&json::parse(&buffer).unwrap()).expect("Grid loaded");
-                } else {
-                    println!("No quick save file");
-                };
+            { // Quick (Save / Load) Handler
+                if rl.is_key_pressed(KeyboardKey::KEY_P) {
+                    println!("Saving Grid!"); // TODO: draw something to the screen
+
+                    let json_string = grid_handler.to_save_json().to_string();
+                    let mut output = fs::File::create(QUICK_SAVE_FILE).expect("File was created");
+                    write!(output, "{}", json_string).expect("Write to file");
+                    grid_handler.save_world();
+
+                    grid_handler.lint();
+                }
+                if rl.is_key_pressed(KeyboardKey::KEY_L) {
+                    println!("Loading Saved Grid!"); // TODO: draw something to the screen
+
+                    if let Ok(mut input) = fs::File::open(QUICK_SAVE_FILE) {
+                        let mut buffer = String::new();
+                        input.read_to_string(&mut buffer).expect("Read to buffer");
+
+                        // not json at all goes the same way as a broken map
+                        let source = json::parse(&buffer).unwrap_or(json::JsonValue::Null);
+                        if let Some(floors) = FloorStack::from_json(&source) {
+                            grid_handler.leave_world();
+                            grid_handler.floors = floors;
+                            grid_handler.floating = None;
+                            grid_handler.pending_link = None;
+                            grid_handler.region_drag = None;
+                            grid_handler.object_drag = None;
+                            grid_handler.selected_object = None;
+                            grid_handler.history.clear();
+                            grid_handler.load_save_definitions(&source);
+                        } else {
+                            println!("Quick save is broken, not loading it");
+                        }
+                    } else {
+                        println!("No quick save file");
+                    };
+                }
             }
-        }
 
-        { // Grid Resizing
-            let grid = &mut grid_handler.grid;
-            if rl.is_key_pressed(KeyboardKey::KEY_S) {                    grid.resize(grid.rows + 1, grid.cols    )  }
-            if rl.is_key_pressed(KeyboardKey::KEY_W) { if grid.rows > 1 { grid.resize(grid.rows - 1, grid.cols    ) }}
-            if rl.is_key_pressed(KeyboardKey::KEY_D) {                    grid.resize(grid.rows    , grid.cols + 1)  }
-            if rl.is_key_pressed(KeyboardKey::KEY_A) { if grid.cols > 1 { grid.resize(grid.rows    , grid.cols - 1) }}
-        }
+            // ctrl+s saves a stamp
+            if !ctrl_down { // Grid Resizing
+                // shift works on the top and left edges instead of the bottom and right
+                let shift_down = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+                let anchor = if shift_down { Anchor::BottomRight } else { Anchor::TopLeft };
+
+                let (rows, cols) = grid_handler.floors.active_floor().size();
+                if rl.is_key_pressed(KeyboardKey::KEY_S) {               grid_handler.resize_active_floor(rows + 1, cols    , anchor)  }
+                if rl.is_key_pressed(KeyboardKey::KEY_W) { if rows > 1 { grid_handler.resize_active_floor(rows - 1, cols    , anchor) }}
+                if rl.is_key_pressed(KeyboardKey::KEY_D) {               grid_handler.resize_active_floor(rows    , cols + 1, anchor)  }
+                if rl.is_key_pressed(KeyboardKey::KEY_A) { if cols > 1 { grid_handler.resize_active_floor(rows    , cols - 1, anchor) }}
+            }
 
-        { // Selection cycling
-            let icon_server = &mut grid_handler.icon_server;
-            if rl.is_key_pressed(KeyboardKey::KEY_E) { icon_server.cycle_selected( 1) }
-            if rl.is_key_pressed(KeyboardKey::KEY_Q) { icon_server.cycle_selected(-1) }
-            if rl.is_key_pressed(KeyboardKey::KEY_X) { icon_server.cycle_default ( 1) }
-            if rl.is_key_pressed(KeyboardKey::KEY_Z) { icon_server.cycle_default (-1) }
-        }
+            { // World
+                // ctrl+W goes in and out, the arrows move the window (a chunk at a time with shift)
+                let shift_down = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+                let step = if shift_down { CHUNK_SIZE as i64 } else { 1 };
+                if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_W) { grid_handler.toggle_world() }
+                if rl.is_key_pressed(KeyboardKey::KEY_LEFT ) { grid_handler.scroll_world(-step, 0) }
+                if rl.is_key_pressed(KeyboardKey::KEY_RIGHT) { grid_handler.scroll_world( step, 0) }
+                if rl.is_key_pressed(KeyboardKey::KEY_UP   ) { grid_handler.scroll_world(0, -step) }
+                if rl.is_key_pressed(KeyboardKey::KEY_DOWN ) { grid_handler.scroll_world(0,  step) }
+            }
+
+            { // Layer cycling
+                if rl.is_key_pressed(KeyboardKey::KEY_TAB) { grid_handler.floors.active_floor_mut().cycle_active_layer(1) }
+            }
+
+            { // Floor switching
+                let floors = &mut grid_handler.floors;
+                if rl.is_key_pressed(KeyboardKey::KEY_PAGE_UP  ) { floors.change_floor( 1) }
+                if rl.is_key_pressed(KeyboardKey::KEY_PAGE_DOWN) { floors.change_floor(-1) }
+                if rl.is_key_pressed(KeyboardKey::KEY_INSERT) { grid_handler.insert_floor_above() }
+            }
+
+            { // Tools
+                let shift_down = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+                // shift+B places objects, they're painted with the selected icon too
+                if rl.is_key_pressed(KeyboardKey::KEY_B) { grid_handler.tool = if shift_down { EditorTool::Object } else { EditorTool::Brush } }
+                if rl.is_key_pressed(KeyboardKey::KEY_F) {
+                    // pressing it again switches how the fill spreads
+                    grid_handler.tool = match grid_handler.tool {
+                        EditorTool::Fill(Connectivity::Four) => EditorTool::Fill(Connectivity::Eight),
+                        _ => EditorTool::Fill(Connectivity::Four),
+                    };
+                }
+                if rl.is_key_pressed(KeyboardKey::KEY_T) && shift_down { grid_handler.cycle_topology() }
+                if rl.is_key_pressed(KeyboardKey::KEY_T) && !shift_down {
+                    // same thing here, goes through the shapes
+                    grid_handler.tool = match grid_handler.tool {
+                        EditorTool::Shape(shape) => EditorTool::Shape(shape.next()),
+                        _ => EditorTool::Shape(Shape::Line),
+                    };
+                }
+                // shift+G for regions, they're like selections that stick around
+                if rl.is_key_pressed(KeyboardKey::KEY_G) && !ctrl_down { grid_handler.tool = if shift_down { EditorTool::Region } else { EditorTool::Select } }
+                if rl.is_key_pressed(KeyboardKey::KEY_I) && shift_down { grid_handler.cycle_projection() }
+                if rl.is_key_pressed(KeyboardKey::KEY_I) && !shift_down {
+                    // goes through the terrain sets
+                    let count = grid_handler.terrains.len();
+                    let is_hex = grid_handler.floors.active_floor().topology != Topology::Square;
+                    grid_handler.tool = match grid_handler.tool {
+                        _ if count == 0 => { println!("No terrain sets loaded"); grid_handler.tool }
+                        _ if is_hex => { println!("Terrain sets only work on square maps"); grid_handler.tool }
+                        EditorTool::Terrain(index) => EditorTool::Terrain((index + 1) % count),
+                        _ => EditorTool::Terrain(0),
+                    };
+                }
+
+                // R turns the brush, shift+R mirrors it
+                let brush = &mut grid_handler.brush_orientation;
+                if rl.is_key_pressed(KeyboardKey::KEY_R) { *brush = if shift_down { brush.flipped() } else { brush.rotated() } }
+                if !matches!(grid_handler.tool, EditorTool::Shape(_)) { grid_handler.shape_drag = None }
+                if grid_handler.tool != EditorTool::Region { grid_handler.finish_region_drag() }
+                if grid_handler.tool != EditorTool::Object { grid_handler.finish_object_drag() }
+                if rl.is_key_pressed(KeyboardKey::KEY_DELETE) { grid_handler.delete_selected_object() }
+            }
+
+            { // Rotate / Flip
+                if rl.is_key_pressed(KeyboardKey::KEY_PERIOD) { grid_handler.transform(GridTransform::Rotate90 ) }
+                if rl.is_key_pressed(KeyboardKey::KEY_SLASH ) { grid_handler.transform(GridTransform::Rotate180) }
+                if rl.is_key_pressed(KeyboardKey::KEY_COMMA ) { grid_handler.transform(GridTransform::Rotate270) }
+                if rl.is_key_pressed(KeyboardKey::KEY_H) { grid_handler.transform(GridTransform::FlipHorizontal) }
+                if rl.is_key_pressed(KeyboardKey::KEY_V) && !ctrl_down { grid_handler.transform(GridTransform::FlipVertical) }
+                if rl.is_key_pressed(KeyboardKey::KEY_U) { grid_handler.transform(GridTransform::Transpose) }
+            }
+
+            { // Clipboard
+                if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_C) { grid_handler.copy_selection(&mut rl) }
+                if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_X) { grid_handler.cut_selection(&mut rl) }
+                if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_V) { grid_handler.paste_clipboard(&rl) }
+            }
+
+            { // Stamps
+                if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_S) && grid_handler.active_selection().is_some() {
+                    grid_handler.stamp_name_editor = Some(String::new());
+                }
+                if rl.is_key_pressed(KeyboardKey::KEY_J) { grid_handler.stamp_panel_open = !grid_handler.stamp_panel_open }
+            }
+
+            { // Generate
+                if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_G) { grid_handler.start_proposal() }
+            }
 
-        { // File dialog
-            // TODO: consolidate with mouse events
-            if rl.is_key_pressed(KeyboardKey::KEY_O) {
-                file_dialog_context.is_open = !file_dialog_context.is_open;
-                file_dialog_context.drag_context.is_dragging = false;
+            { // Stats
+                // shift+Y checks the floor can be walked through
+                let shift_down = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+                if rl.is_key_pressed(KeyboardKey::KEY_Y) && !ctrl_down && !shift_down { grid_handler.stats_panel_open = !grid_handler.stats_panel_open }
+                if rl.is_key_pressed(KeyboardKey::KEY_Y) && !ctrl_down &&  shift_down { grid_handler.walk_check_open = !grid_handler.walk_check_open }
+            }
+
+            { // Selection cycling
+                let icon_server = &mut grid_handler.icon_server;
+                if rl.is_key_pressed(KeyboardKey::KEY_E) { icon_server.cycle_selected( 1) }
+                if rl.is_key_pressed(KeyboardKey::KEY_Q) { icon_server.cycle_selected(-1) }
+                if rl.is_key_pressed(KeyboardKey::KEY_X) && !ctrl_down { icon_server.cycle_default ( 1) }
+                if rl.is_key_pressed(KeyboardKey::KEY_Z) && !ctrl_down { icon_server.cycle_default (-1) }
+            }
+
+            { // Pallet manifest
+                if rl.is_key_pressed(KeyboardKey::KEY_M) {
+                    println!("Saving Pallet Manifests!");
+                    grid_handler.save_manifests();
+                }
+            }
 
-                // if there are any "..", reset back to start.
-                if file_dialog_context.current_path.components().any(|p| p == Component::ParentDir) {
-                    file_dialog_context.current_path = ".".into();
+            { // File dialog
+                // TODO: consolidate with mouse events
+                if rl.is_key_pressed(KeyboardKey::KEY_O) {
+                    file_dialog_context.is_open = !file_dialog_context.is_open;
+                    file_dialog_context.drag_context.is_dragging = false;
+
+                    // if there are any "..", reset back to start.
+                    if file_dialog_context.current_path.components().any(|p| p == Component::ParentDir) {
+                        file_dialog_context.current_path = ".".into();
+                    }
                 }
             }
         }
@@ -143,11 +604,46 @@ fn main() {
                 mouse_context = MouseContext::inactive();
             }
 
+            grid_handler.update_stamp_panel(&mouse_context, &mut rl);
+            if grid_handler.to_stamp_panel(&mut rl).is_some_and(|panel| panel.mouse_over_panel(&mouse_context)) {
+                mouse_context = MouseContext::inactive();
+            }
+
+            grid_handler.update_stats_panel(&mouse_context, &mut rl);
+            if grid_handler.to_stats_panel(&mut rl).is_some_and(|panel| panel.mouse_over_panel(&mouse_context)) {
+                mouse_context = MouseContext::inactive();
+            }
+
+            grid_handler.update_lint_panel(&mouse_context, &mut rl);
+            if grid_handler.to_lint_panel(&mut rl).is_some_and(|panel| panel.mouse_over_panel(&mouse_context)) {
+                mouse_context = MouseContext::inactive();
+            }
+
+            grid_handler.update_proposal_panel(&mouse_context, &mut rl);
+            if grid_handler.to_proposal_panel(&mut rl).is_some_and(|panel| panel.mouse_over_panel(&mouse_context)) {
+                mouse_context = MouseContext::inactive();
+            }
+
             grid_handler.update_pallet(&mouse_context);
 
             grid_handler.update_grid(&mouse_context);
+
+            grid_handler.update_links(&mouse_context, !typing && rl.is_key_pressed(KeyboardKey::KEY_K));
+
+            grid_handler.update_notes(&mouse_context, !typing && rl.is_key_pressed(KeyboardKey::KEY_N));
+
+            // C swaps every copy of the hovered tile for the selected one, shift+C does every layer
+            let ctrl_down = rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+            let shift_down = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+            grid_handler.update_replace(&mouse_context, !typing && !ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_C), shift_down);
+
+            grid_handler.update_walk_report();
         }
 
+        // a release over a panel (or while something's floating) never gets to update_objects,
+        // so the object drag ends on the real mouse, wherever the button comes up
+        if !mouse_context.mouse_left_down { grid_handler.finish_object_drag() }
+
         /* -------------------- LOAD TEXTURES -------------------- */
         if textures_dirty {
             for (_, image_container) in grid_handler.icon_server.assets.iter_mut() {
@@ -175,12 +671,79 @@ fn main() {
             
             let pallet_panel = grid_handler.to_pallet_panel();
             window_panel.add(Box::new(&pallet_panel));
+
+            let stamp_panel = grid_handler.to_stamp_panel(&mut d);
+            if let Some(stamp_panel) = stamp_panel.as_ref() {
+                window_panel.add(Box::new(stamp_panel));
+            }
+
+            let stats_panel = grid_handler.to_stats_panel(&mut d);
+            if let Some(stats_panel) = stats_panel.as_ref() {
+                window_panel.add(Box::new(stats_panel));
+            }
+
+            let walk_panel = grid_handler.to_walk_panel(&mut d);
+            if let Some(walk_panel) = walk_panel.as_ref() {
+                window_panel.add(Box::new(walk_panel));
+            }
+
+            let lint_panel = grid_handler.to_lint_panel(&mut d);
+            if let Some(lint_panel) = lint_panel.as_ref() {
+                window_panel.add(Box::new(lint_panel));
+            }
+
+            let proposal_panel = grid_handler.to_proposal_panel(&mut d);
+            if let Some(proposal_panel) = proposal_panel.as_ref() {
+                window_panel.add(Box::new(proposal_panel));
+            }
             
             // TODO: Move the grid out of the way
             let grid_panel = grid_handler.to_grid_panel();
             window_panel.add(Box::new(&grid_panel));
 
             window_panel.draw_panel(&mut d, &mouse_context);
+
+            grid_handler.draw_regions(&mut d, &grid_panel);
+
+            grid_handler.draw_objects(&mut d, &grid_panel);
+
+            grid_handler.draw_links(&mut d, &grid_panel);
+
+            grid_handler.draw_notes(&mut d, &grid_panel, &mouse_context);
+
+            if let Some(name) = grid_handler.stamp_name_editor.as_ref() {
+                draw_text_box(&mut d, &[format!("Stamp name: {name}_")], mouse_context.mouse_pos);
+            }
+            if let Some((target, name)) = grid_handler.name_editor.as_ref() {
+                let label = match target {
+                    NameTarget::Region(_) => "Region",
+                    NameTarget::Object(_) => "Object",
+                    NameTarget::Link(_) => "Link",
+                };
+                draw_text_box(&mut d, &[format!("{label} name: {name}_")], mouse_context.mouse_pos);
+            }
+
+            if let Some(inspector_panel) = grid_handler.to_inspector_panel(&mouse_context, &mut d) {
+                inspector_panel.draw_panel(&mut d, &MouseContext::inactive());
+            }
+
+            let floors = &grid_handler.floors;
+            let world_text = match grid_handler.world.as_ref() {
+                Some(view) => format!("  World: ({},{})", view.origin.0, view.origin.1),
+                None => String::new(),
+            };
+            let status_text = format!(
+                "Floor: {}/{}  Layer: {}  Grid: {}, {}  Tool: {}  Turn: {}{}{world_text}",
+                floors.active_floor_index() + 1, floors.floors().len(),
+                floors.active_floor().active_layer().name,
+                floors.active_floor().topology.name(),
+                floors.active_floor().projection.name(),
+                grid_handler.tool_name(),
+                grid_handler.brush_orientation.quarter_turns as u32 * 90,
+                if grid_handler.brush_orientation.flip_x { " flipped" } else { "" }
+            );
+            let text_y = GRID_START_POSITION.y as i32 - TEXT_SIZE - TEXT_PADDING;
+            d.draw_text(&status_text, GRID_START_POSITION.x as i32, text_y, TEXT_SIZE, Color::BLACK);
         }
         /* -------------------- DRAWING END -------------------- */
     }
@@ -193,6 +756,52 @@ impl ToAndFromJsonValue for String {
     }
 }
 
+// objects are measured in cells, but the grid panel has gaps between them.
+// the middle of a cell lands on the middle of its square
+fn point_to_screen<T: DrawableObject>(grid_panel: &GridPanel<T>, point: (f32, f32)) -> Vector2 {
+    let origin = grid_panel.get_position();
+    if let Some(layout) = grid_panel.iso_layout() {
+        let (x, y) = layout.point_to_screen(point);
+        return origin + Vector2::new(x, y);
+    }
+
+    let pitch_x = (grid_panel.item_width  + grid_panel.item_padding) as f32;
+    let pitch_y = (grid_panel.item_height + grid_panel.item_padding) as f32;
+    let half_gap = grid_panel.item_padding as f32 / 2.0;
+
+    Vector2::new(origin.x + point.0 * pitch_x - half_gap, origin.y + point.1 * pitch_y - half_gap)
+}
+
+fn screen_to_point<T: DrawableObject>(grid_panel: &GridPanel<T>, screen: Vector2) -> (f32, f32) {
+    let origin = grid_panel.get_position();
+    if let Some(layout) = grid_panel.iso_layout() {
+        let screen = screen - origin;
+        return layout.screen_to_point((screen.x, screen.y));
+    }
+
+    let pitch_x = (grid_panel.item_width  + grid_panel.item_padding) as f32;
+    let pitch_y = (grid_panel.item_height + grid_panel.item_padding) as f32;
+    let half_gap = grid_panel.item_padding as f32 / 2.0;
+
+    ((screen.x - origin.x + half_gap) / pitch_x, (screen.y - origin.y + half_gap) / pitch_y)
+}
+
+// a box of text just below and right of position, for notes and prompts
+fn draw_text_box(d: &mut RaylibDrawHandle, lines: &[String], position: Vector2) {
+    if lines.is_empty() { return; }
+
+    let width = lines.iter().map(|line| d.measure_text(line, TEXT_SIZE)).max().unwrap_or(0);
+    let height = lines.len() as i32 * TEXT_SIZE;
+
+    let x = position.x as i32 + TEXT_PADDING;
+    let y = position.y as i32 + TEXT_PADDING;
+
+    d.draw_rectangle(x, y, width + TEXT_PADDING * 2, height + TEXT_PADDING * 2, NOTE_BACKGROUND_COLOR);
+    for (i, line) in lines.iter().enumerate() {
+        d.draw_text(line, x + TEXT_PADDING, y + TEXT_PADDING + i as i32 * TEXT_SIZE, TEXT_SIZE, NOTE_TEXT_COLOR);
+    }
+}
+
 fn get_image_from_path(path: &Path) -> (String, ImageContainer) {
     let name = path.to_str().expect("Valid path").to_string();
 
@@ -203,31 +812,31 @@ fn get_image_from_path(path: &Path) -> (String, ImageContainer) {
 fn get_images_from_path(path: &Path) -> Vec<(String, ImageContainer)> {
     let paths = fs::read_dir(path).expect("Valid directory");
 
+    // the pallet manifest and terrain sets live in here too, leave out the json.
+    // anything else goes to raylib, so every format it reads still works
     let names: Vec<_> = paths
         .map(|path| path.unwrap())
+        .filter(|path| path.path().extension().and_then(|extension| extension.to_str()) != Some("json"))
         .map(|path|
             path.path().to_str().expect("Valid path").to_string()
         )
         .collect();
-    
-    let images: Vec<_> = names
-        .iter()
-        .map(|path|
-            raylib::texture::Image::load_image(&path).expect("Is Valid Image")
-        )
-        .collect();
 
     names.into_iter()
-        .zip(images)
-        .map(|(s, image)|
-            (s, ImageContainer { image, texture: None }
-        ))
+        .filter_map(|name| match raylib::texture::Image::load_image(&name) {
+            Ok(image) => Some((name, ImageContainer { image, texture: None })),
+            Err(error) => {
+                println!("Skipping {name}, not an image: {error}");
+                None
+            }
+        })
         .collect()
 }
 
 impl<'a> GridHandler {
-    fn to_grid_panel(&'a self) -> GridPanel<&'a Texture2D> {
-        let (rows, cols) = self.grid.size();
+    fn to_grid_panel(&'a self) -> GridPanel<Vec<OrientedTexture<'a>>> {
+        let map = self.floors.active_floor();
+        let (rows, cols) = map.size();
         
         let mut panel = GridPanel::new_custom(
             GRID_START_POSITION,
@@ -235,25 +844,110 @@ impl<'a> GridHandler {
             64, 64, 10,
             Some(Color::ORANGE), None
         );
+        panel.topology = map.topology;
+        panel.projection = map.projection;
+
+        let overlay = self.grid_overlay();
+        let check_highlights = self.check_highlights();
 
         for i in 0..rows*cols {
-            let image_container = if let Some(name) = self.grid.get_from_index(i) {
-                self.icon_server.get_by_name(name).expect("Name exist in icon server")
-            } else {
-                self.icon_server.get_default_handle()
-            };
-    
-            let Some(texture) = image_container.texture.as_ref() else {
+            let pos = index_to_pos(i, map.size());
+
+            // bottom to top, empty ground shows the default icon underneath
+            let mut image_containers = vec![];
+            if map.layers()[0].grid.get(pos).is_none() {
+                image_containers.push((self.icon_server.get_default_handle(), Orientation::default()));
+            }
+            for (name, orientation) in map.get_stack(pos) {
+                image_containers.push((self.icon_server.get_by_name(name).expect("Name exist in icon server"), orientation));
+            }
+
+            // whatever is being dragged around goes on top
+            let extra = overlay.get(&pos);
+            if let Some((Some((name, orientation)), _)) = extra {
+                image_containers.push((self.icon_server.get_by_name(name).expect("Name exist in icon server"), *orientation));
+            }
+
+            let Some(textures) = image_containers
+                .iter()
+                .map(|(image_container, orientation)| Some(OrientedTexture {
+                    texture: image_container.texture.as_ref()?,
+                    quarter_turns: orientation.quarter_turns,
+                    flip_x: orientation.flip_x,
+                    flip_y: orientation.flip_y,
+                }))
+                .collect::<Option<Vec<_>>>()
+            else {
                 panel.add_none();
                 continue;
             };
     
-            panel.add(texture);
+            let highlights: Vec<Color> = check_highlights.get(&pos).into_iter().chain(extra.map(|(_, color)| color)).copied().collect();
+            if highlights.is_empty() {
+                panel.add(textures);
+            } else {
+                panel.add_with_highlight(textures, &highlights);
+            }
         }
     
         return panel;
     }
 
+    // things drawn over the grid that aren't in it (yet). later ones win
+    // Returns the extra item to draw on each cell, and its highlight
+    fn grid_overlay(&self) -> GridOverlay<'_> {
+        let mut overlay = BTreeMap::new();
+        let (rows, cols) = self.floors.active_floor().size();
+
+        if let Some((start, end)) = self.active_selection() {
+            for pos in rect_filled(start, end) {
+                overlay.insert(pos, (None, SELECTION_COLOR));
+            }
+        }
+
+        // erasing just gets marked
+        if let Some(drag) = self.shape_drag.as_ref() {
+            let (item, color) = if drag.erase {
+                (None, SHAPE_ERASE_COLOR)
+            } else {
+                (Some((self.icon_server.get_selected_name(), self.brush_orientation)), SHAPE_PREVIEW_COLOR)
+            };
+            for pos in drag.shape.cells(drag.start, drag.end) {
+                overlay.insert(pos, (item, color));
+            }
+        }
+
+        if let Some(proposal) = self.active_proposal() {
+            let ((min_x, min_y), _) = proposal.area;
+            match proposal.result.as_ref() {
+                Ok(grid) => for y in 0..grid.rows {
+                    for x in 0..grid.cols {
+                        let item = grid.get((x, y)).as_deref().map(|name| (name, grid.get_orientation((x, y))));
+                        overlay.insert((min_x + x, min_y + y), (item, PROPOSAL_COLOR));
+                    }
+                },
+                Err(contradiction) => {
+                    let (x, y) = contradiction.pos;
+                    overlay.insert((min_x + x, min_y + y), (None, CONTRADICTION_COLOR));
+                }
+            }
+        }
+
+        if let Some(floating) = self.floating.as_ref() {
+            for y in 0..floating.grid.rows {
+                for x in 0..floating.grid.cols {
+                    let pos = (floating.origin.0 + x, floating.origin.1 + y);
+                    if pos.0 >= cols || pos.1 >= rows { continue; }
+
+                    let item = floating.grid.get((x, y)).as_deref().map(|name| (name, floating.grid.get_orientation((x, y))));
+                    overlay.insert(pos, (item, FLOATING_COLOR));
+                }
+            }
+        }
+
+        return overlay;
+    }
+
     fn to_pallet_panel(&self) -> GridPanel<&Texture2D> {
         let mut panel = GridPanel::new_custom(
             PALLET_START_POSITION,
@@ -284,31 +978,1503 @@ impl<'a> GridHandler {
 
         let id = grid_pallet.get_hovered_id(mouse_context);
 
-        let Some(id) = id else { return; };
+        if self.floating.as_ref().is_some_and(|floating| floating.target != self.active_target()) {
+            self.cancel_floating();
+        }
 
-        let pos = index_to_pos(id, self.grid.size());
+        // something floating takes the mouse, whatever the tool
+        if self.floating.is_some() {
+            self.update_floating(id, mouse_context);
+            return;
+        }
 
-        if mouse_context.mouse_left_pressed {
-            self.grid.set(pos, Some(self.icon_server.get_selected_name().to_string()));
+        match self.tool {
+            EditorTool::Brush | EditorTool::Terrain(_) => self.update_brush(id, mouse_context),
+            EditorTool::Fill(connectivity) => {
+                let Some(id) = id else { return; };
+                let pos = index_to_pos(id, self.floors.active_floor().size());
+
+                // hex maps always spread to all six
+                let connectivity = self.floors.active_floor().topology.connectivity(connectivity);
+
+                if mouse_context.mouse_left_pressed {
+                    let item = Some(self.icon_server.get_selected_name().to_string());
+                    let orientation = self.brush_orientation;
+                    self.edit_active_grid(|grid| { grid.flood_fill(pos, item, orientation, connectivity); });
+                }
+                if mouse_context.mouse_right_pressed {
+                    self.edit_active_grid(|grid| { grid.flood_fill(pos, None, Orientation::default(), connectivity); });
+                }
+            }
+            EditorTool::Shape(shape) => self.update_shape(shape, id, mouse_context),
+            EditorTool::Select => self.update_select(id, mouse_context),
+            EditorTool::Region => self.update_region(id, mouse_context),
+            EditorTool::Object => self.update_objects(id, mouse_context),
         }
+    }
+
+    // the selection, if it still fits on the grid you're looking at. Returns (min, max) corners
+    fn active_selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let (start, end) = self.selection?;
+        let (min_x, min_y, max_x, max_y) = bounding_box(start, end);
+
+        let (rows, cols) = self.floors.active_floor().size();
+        if max_x >= cols || max_y >= rows { return None; }
+
+        return Some(((min_x, min_y), (max_x, max_y)));
+    }
+
+    fn update_select(&mut self, id: Option<usize>, mouse_context: &MouseContext) {
+        let pos = id.map(|id| index_to_pos(id, self.floors.active_floor().size()));
+
         if mouse_context.mouse_right_pressed {
-            self.grid.set(pos, None);
+            self.selection = None;
+            self.selecting = false;
+        }
+
+        if let (Some(pos), true) = (pos, mouse_context.mouse_left_pressed) {
+            // grabbing inside the selection moves it, anywhere else starts a new one
+            match self.active_selection() {
+                Some((min, max)) if min.0 <= pos.0 && pos.0 <= max.0 && min.1 <= pos.1 && pos.1 <= max.1 => {
+                    self.lift_selection(min, max, pos);
+                    return;
+                }
+                _ => {
+                    self.selection = Some((pos, pos));
+                    self.selecting = true;
+                }
+            }
+        }
+
+        if !mouse_context.mouse_left_down { self.selecting = false; }
+        if !self.selecting { return; }
+
+        if let (Some(pos), Some(selection)) = (pos, self.selection.as_mut()) {
+            selection.1 = pos;
         }
     }
 
-    pub fn update_pallet(&mut self, mouse_context: &MouseContext) {
-        let pallet_panel = self.to_pallet_panel();
+    // cut without the clipboard, the cells ride on the mouse until it lets go
+    fn lift_selection(&mut self, min: (usize, usize), max: (usize, usize), grab_pos: (usize, usize)) {
+        let grid = self.floors.active_floor().active_grid().extract(min, max);
+
+        // picking up and putting down is one undo
+        self.history.begin_step();
+        self.edit_active_grid(|grid| grid.clear_area(min, max));
+
+        self.floating = Some(FloatingGrid {
+            grid,
+            target: self.active_target(),
+            origin: min,
+            grab: (grab_pos.0 - min.0, grab_pos.1 - min.1),
+            home: Some(min),
+        });
+        self.selection = None;
+    }
 
-        let Some(id) = pallet_panel.get_hovered_id(&mouse_context) else { return; };
+    // pastes get dropped with a click, moves when the button comes up.
+    // right click cancels, a move goes back where it came from
+    fn update_floating(&mut self, id: Option<usize>, mouse_context: &MouseContext) {
+        let (rows, cols) = self.floors.active_floor().size();
+        let floating = self.floating.as_mut().expect("Only called when floating");
 
-        let name = self.icon_server.assets[id].0.clone();
+        // the top and left edges stop it, the other ones cut it off
+        if let Some(id) = id {
+            let (x, y) = index_to_pos(id, (rows, cols));
+            floating.origin = (x.saturating_sub(floating.grab.0), y.saturating_sub(floating.grab.1));
+        }
 
-        if mouse_context.mouse_left_pressed {
-            self.icon_server.set_selected_by_name(&name);
+        let place = match floating.home {
+            Some(_) => !mouse_context.mouse_left_down,
+            None    => mouse_context.mouse_left_pressed && id.is_some(),
+        };
+        let cancel = mouse_context.mouse_right_pressed;
+        if !place && !cancel { return; }
+
+        let floating = self.floating.take().expect("Checked above");
+        let origin = if place { floating.origin } else { floating.home.unwrap_or(floating.origin) };
+
+        if place || floating.home.is_some() {
+            self.edit_active_grid(|grid| grid.paste(origin, &floating.grid));
+            let end = (
+                (origin.0 + floating.grid.cols - 1).min(cols - 1),
+                (origin.1 + floating.grid.rows - 1).min(rows - 1),
+            );
+            self.selection = Some((origin, end));
         }
-        if mouse_context.mouse_right_pressed {
-            self.icon_server.set_default_by_name(&name);
+        self.history.end_step();
+    }
+
+    // a move goes back where it was lifted from, in the grid it came from. a paste just goes away
+    fn cancel_floating(&mut self) {
+        let Some(floating) = self.floating.take() else { return; };
+        if let Some(home) = floating.home {
+            self.edit_grid(floating.target, |grid| grid.paste(home, &floating.grid));
+        }
+        self.history.end_step();
+    }
+
+    // the system clipboard gets the same json a grid saves as,
+    // so another editor can paste it
+    fn copy_selection(&mut self, rl: &mut RaylibHandle) {
+        let Some((min, max)) = self.active_selection() else { return; };
+
+        let grid = self.floors.active_floor().active_grid().extract(min, max);
+        if rl.set_clipboard_text(&grid.to_json().dump()).is_err() {
+            println!("Couldn't copy to the system clipboard");
+        }
+        self.clipboard = Some(grid);
+    }
+
+    fn cut_selection(&mut self, rl: &mut RaylibHandle) {
+        let Some((min, max)) = self.active_selection() else { return; };
+
+        self.copy_selection(rl);
+        self.edit_active_grid(|grid| grid.clear_area(min, max));
+    }
+
+    // the system clipboard first, it might be from another editor
+    fn paste_clipboard(&mut self, rl: &RaylibHandle) {
+        let from_system = rl.get_clipboard_text()
+            .ok()
+            .and_then(|text| json::parse(&text).ok())
+            // anything that isn't a grid gets ignored
+            .and_then(|source| TileGrid::<String>::from_json(&source));
+
+        let Some(grid) = from_system.or_else(|| self.clipboard.clone()) else { return; };
+        self.start_paste(grid);
+    }
+
+    // puts grid on the mouse, to be clicked down somewhere
+    fn start_paste(&mut self, mut grid: TileGrid<String>) {
+        // it might be from another editor, with icons we don't have
+        for i in 0..grid.rows * grid.cols {
+            let pos = index_to_pos(i, grid.size());
+            let Some(name) = grid.get(pos) else { continue; };
+            if self.icon_server.get_by_name(name).is_none() {
+                println!("Pasted tile {name} isn't loaded, leaving it out");
+                grid.set(pos, None);
+            }
+        }
+
+        self.floating = Some(FloatingGrid { grid, target: self.active_target(), origin: (0, 0), grab: (0, 0), home: None });
+        self.shape_drag = None;
+    }
+
+    fn load_stamps(&mut self) {
+        self.stamps = load_stamps(Path::new(STAMP_PATH));
+
+        for stamp in self.stamps.iter() {
+            let missing = stamp.missing_tiles(|name| self.icon_server.get_by_name(name).is_some());
+            if !missing.is_empty() {
+                println!("Warning: stamp {} uses tiles that aren't loaded: {}", stamp.name, missing.iter().map(|name| name.as_str()).collect::<Vec<_>>().join(", "));
+            }
+        }
+    }
+
+    // Returns None when it's closed
+    fn to_stamp_panel(&self, rl: &mut impl CanMeasureText) -> Option<TextPanel> {
+        if !self.stamp_panel_open { return None; }
+
+        let any_missing = self.stamps
+            .iter()
+            .any(|stamp| !stamp.missing_tiles(|name| self.icon_server.get_by_name(name).is_some()).is_empty());
+
+        // the whole panel goes red if something in it can't be drawn
+        let mut panel = TextPanel::new_custom(
+            TEXT_SIZE,
+            TEXT_PADDING,
+            INSPECTOR_ITEM_PADDING,
+            if any_missing { STAMP_PANEL_MISSING_COLOR } else { STAMP_PANEL_BACKGROUND_COLOR },
+            STAMP_PANEL_TEXT_COLOR,
+            Some(STAMP_PANEL_HOVER_COLOR)
+        );
+
+        if self.stamps.is_empty() {
+            panel.add_text_button("no stamps, Ctrl+S saves the selection", rl);
+        }
+        for stamp in self.stamps.iter() {
+            let missing = stamp.missing_tiles(|name| self.icon_server.get_by_name(name).is_some());
+            let label = match missing.len() {
+                0 => stamp.name.clone(),
+                count => format!("{} ({count} tiles missing)", stamp.name),
+            };
+            panel.add_text_button(&label, rl);
+        }
+
+        // up against the right side of the window
+        let x = WINDOW_WIDTH as f32 - panel.as_rec().width - TEXT_PADDING as f32;
+        panel.set_position(Vector2::new(x, TEXT_PADDING as f32));
+
+        return Some(panel);
+    }
+
+    fn update_stamp_panel(&mut self, mouse_context: &MouseContext, rl: &mut impl CanMeasureText) {
+        let Some(panel) = self.to_stamp_panel(rl) else { return; };
+        if !mouse_context.mouse_left_pressed { return; }
+
+        let Some(stamp) = panel.get_hovered_id(mouse_context).and_then(|id| self.stamps.get(id)) else { return; };
+        let grid = stamp.grid.clone();
+        self.start_paste(grid);
+    }
+
+    // the active layer, with buttons to save it all out
+    fn to_stats_panel(&self, rl: &mut impl CanMeasureText) -> Option<TextPanel> {
+        if !self.stats_panel_open { return None; }
+
+        let mut panel = TextPanel::new_custom(
+            TEXT_SIZE,
+            TEXT_PADDING,
+            INSPECTOR_ITEM_PADDING,
+            STAMP_PANEL_BACKGROUND_COLOR,
+            STAMP_PANEL_TEXT_COLOR,
+            Some(STAMP_PANEL_HOVER_COLOR)
+        );
+
+        let floor = self.floors.active_floor();
+        panel.add_text_button(&format!("Layer: {}", floor.active_layer().name), rl);
+        for line in tile_stats(floor.active_grid(), &self.icon_server).lines() {
+            panel.add_text_button(&line, rl);
         }
+        panel.add_text_buttons(&["Export CSV", "Export JSON"], rl);
+
+        // bottom right, out of the way of the stamp list
+        let rec = panel.as_rec();
+        let position = Vector2::new(
+            WINDOW_WIDTH  as f32 - rec.width  - TEXT_PADDING as f32,
+            WINDOW_HEIGHT as f32 - rec.height - TEXT_PADDING as f32,
+        );
+        panel.set_position(position);
+
+        return Some(panel);
+    }
+
+    // the report from the last update_walk_report, only rebuilt when the floor changes
+    fn walk_report(&self) -> Option<&WalkReport> {
+        self.walk_report.as_ref().map(|(_, report)| report)
+    }
+
+    // comparing the floor is a lot cheaper than the path finding
+    fn update_walk_report(&mut self) {
+        if !self.walk_check_open {
+            self.walk_report = None;
+            return;
+        }
+
+        let floor = self.floors.active_floor();
+        if self.walk_report.as_ref().is_some_and(|(checked, _)| checked == floor) { return; }
+
+        let report = check_floor(floor, |name| self.icon_server.get_definition(name));
+        self.walk_report = Some((floor.clone(), report));
+    }
+
+    // the walkability check and lint results on the active floor. one color per cell at most,
+    // the grid panel only has room for so many
+    fn check_highlights(&self) -> BTreeMap<(usize, usize), Color> {
+        let mut highlights = BTreeMap::new();
+
+        if let Some(violations) = self.lint_violations.as_ref() {
+            let floor = self.floors.active_floor_index();
+            let size = self.floors.active_floor().size();
+            for violation in violations.iter().filter(|violation| violation.floor == Some(floor)) {
+                // the map might have been resized since the save
+                let Some(pos) = violation.pos.filter(|pos| pos.0 < size.1 && pos.1 < size.0) else { continue; };
+                highlights.insert(pos, LINT_VIOLATION_COLOR);
+            }
+        }
+
+        let Some(report) = self.walk_report() else { return highlights; };
+
+        for area in report.areas.iter().skip(1) {
+            highlights.extend(area.iter().map(|pos| (*pos, WALK_CUT_OFF_COLOR)));
+        }
+        for check in report.paths.iter() {
+            match check.path.as_ref() {
+                Some(path) => highlights.extend(path.iter().map(|pos| (*pos, WALK_PATH_COLOR))),
+                None => highlights.extend([(check.start, WALK_UNREACHABLE_COLOR), (check.goal, WALK_UNREACHABLE_COLOR)]),
+            }
+        }
+        return highlights;
+    }
+
+    // bottom left, the stats panel has the other corner
+    fn to_walk_panel(&self, rl: &mut impl CanMeasureText) -> Option<TextPanel> {
+        let report = self.walk_report()?;
+
+        let mut panel = TextPanel::new_custom(
+            TEXT_SIZE,
+            TEXT_PADDING,
+            INSPECTOR_ITEM_PADDING,
+            STAMP_PANEL_BACKGROUND_COLOR,
+            STAMP_PANEL_TEXT_COLOR,
+            None
+        );
+
+        panel.add_text_button(if report.is_ok() { "Walkable: ok" } else { "Walkable: FAILED" }, rl);
+        for line in report.lines() {
+            panel.add_text_button(&line, rl);
+        }
+
+        let rec = panel.as_rec();
+        panel.set_position(Vector2::new(TEXT_PADDING as f32, WINDOW_HEIGHT as f32 - rec.height - TEXT_PADDING as f32));
+
+        return Some(panel);
+    }
+
+    // the selection, or the whole active layer. a fresh seed every time, it's shown so a good one can be kept.
+    // starts on wave function collapse, or caves if there's nothing for it to learn from
+    fn start_proposal(&mut self) {
+        self.cancel_floating();
+        let (rows, cols) = self.floors.active_floor().size();
+        let area = self.active_selection().unwrap_or(((0, 0), (cols - 1, rows - 1)));
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time goes forward").as_nanos() as u64;
+
+        let has_content = self.floors.active_floor().active_grid().items().next().is_some();
+        let mut proposal = Proposal {
+            target: self.active_target(),
+            area,
+            generator: Generator::Wfc,
+            wall: Some(self.icon_server.get_selected_name().to_owned()),
+            floor: None,
+            only_empty: has_content,
+            seed,
+            result: Err(WfcContradiction { pos: (0, 0), seed }),
+        };
+        while !self.generate_proposal(&mut proposal) {
+            proposal.generator = proposal.generator.next();
+        }
+
+        self.proposal = Some(proposal);
+        self.shape_drag = None;
+    }
+
+    // wave function collapse learns from every stamp and the active layer as it is now.
+    // Returns false if there was nothing to learn from, caves and dungeons always work
+    fn generate_proposal(&self, proposal: &mut Proposal) -> bool {
+        let (min, max) = proposal.area;
+        let active_grid = self.floors.active_floor().active_grid();
+        let (rows, cols) = (max.1 - min.1 + 1, max.0 - min.0 + 1);
+
+        let generated = match proposal.generator {
+            Generator::Wfc => return self.generate_wfc_proposal(proposal),
+            Generator::Caves(settings) => generate_cave(rows, cols, proposal.wall.clone(), proposal.floor.clone(), settings, proposal.seed),
+            Generator::Dungeon(settings) => generate_dungeon(rows, cols, proposal.wall.clone(), proposal.floor.clone(), settings, proposal.seed),
+        };
+
+        let mut result = active_grid.extract(min, max);
+        for i in 0..rows * cols {
+            let pos = index_to_pos(i, result.size());
+            if proposal.only_empty && result.get(pos).is_some() { continue; }
+            result.set(pos, generated.get(pos).clone());
+        }
+        proposal.result = Ok(result);
+        return true;
+    }
+
+    fn generate_wfc_proposal(&self, proposal: &mut Proposal) -> bool {
+        // tiles we can't draw would break the preview
+        let loaded = |grid: &TileGrid<String>| {
+            let mut grid = grid.clone();
+            for i in 0..grid.rows * grid.cols {
+                let pos = index_to_pos(i, grid.size());
+                if grid.get(pos).as_ref().is_some_and(|name| self.icon_server.get_by_name(name).is_none()) {
+                    grid.set(pos, None);
+                }
+            }
+            grid
+        };
+        let active_grid = self.floors.active_floor().active_grid();
+        let examples: Vec<TileGrid<String>> = self.stamps.iter().map(|stamp| loaded(&stamp.grid)).chain([active_grid.clone()]).collect();
+
+        let model = WfcModel::learn(&examples.iter().collect::<Vec<_>>());
+        if model.is_empty() {
+            println!("Nothing to learn from, paint something or save some stamps first");
+            return false;
+        }
+
+        let (min, max) = proposal.area;
+        proposal.result = model.generate(&active_grid.extract(min, max), proposal.only_empty, proposal.seed);
+        if let Err(contradiction) = proposal.result.as_ref() {
+            println!("Generator hit a {contradiction}, reroll to try another seed");
+        }
+        return true;
+    }
+
+    // only while you're still looking at the grid it was made for
+    fn active_proposal(&self) -> Option<&Proposal> {
+        let proposal = self.proposal.as_ref()?;
+        let (rows, cols) = self.floors.active_floor().size();
+        let (_, max) = proposal.area;
+
+        if proposal.target != self.active_target() || max.0 >= cols || max.1 >= rows { return None; }
+        return Some(proposal);
+    }
+
+    fn to_proposal_panel(&self, rl: &mut impl CanMeasureText) -> Option<TextPanel> {
+        let proposal = self.active_proposal()?;
+
+        let mut panel = TextPanel::new_custom(
+            TEXT_SIZE,
+            TEXT_PADDING,
+            INSPECTOR_ITEM_PADDING,
+            STAMP_PANEL_BACKGROUND_COLOR,
+            STAMP_PANEL_TEXT_COLOR,
+            Some(STAMP_PANEL_HOVER_COLOR)
+        );
+
+        match proposal.result.as_ref() {
+            Ok(_) => panel.add_text_button(&format!("Generated with seed {}", proposal.seed), rl),
+            Err(contradiction) => panel.add_text_button(&format!("Stuck: {contradiction}"), rl),
+        }
+        panel.add_text_button(&format!("Generator: {}", proposal.generator.name()), rl);
+
+        // clicking one of these sets it to the tile selected in the pallet
+        let tile_name = |tile: &Option<String>| tile.clone().unwrap_or("empty".to_owned());
+        match proposal.generator {
+            Generator::Wfc => {}
+            Generator::Caves(settings) => {
+                panel.add_text_button(&format!("Walls: {}", tile_name(&proposal.wall)), rl);
+                panel.add_text_button(&format!("Floor: {}", tile_name(&proposal.floor)), rl);
+                panel.add_text_button(&format!("Starting walls: {}%", (settings.fill_chance * 100.0).round()), rl);
+                panel.add_text_button(&format!("Smoothing: {} steps", settings.steps), rl);
+            }
+            Generator::Dungeon(settings) => {
+                panel.add_text_button(&format!("Walls: {}", tile_name(&proposal.wall)), rl);
+                panel.add_text_button(&format!("Floor: {}", tile_name(&proposal.floor)), rl);
+                panel.add_text_button(&format!("Smallest room: {}", settings.min_room), rl);
+                panel.add_text_button(&format!("Smallest split: {}", settings.min_leaf), rl);
+            }
+        }
+
+        let mode = if proposal.only_empty { "Filling: empty cells" } else { "Filling: everything" };
+        panel.add_text_buttons(&[mode, "Accept", "Reroll", "Cancel"], rl);
+
+        // top middle, the other panels have the corners
+        let x = (WINDOW_WIDTH as f32 - panel.as_rec().width) / 2.0;
+        panel.set_position(Vector2::new(x, TEXT_PADDING as f32));
+
+        return Some(panel);
+    }
+
+    fn update_proposal_panel(&mut self, mouse_context: &MouseContext, rl: &mut impl CanMeasureText) {
+        if self.proposal.is_some() && self.active_proposal().is_none() {
+            self.proposal = None;
+        }
+        let Some(panel) = self.to_proposal_panel(rl) else { return; };
+        if !mouse_context.mouse_left_pressed { return; }
+        let Some(button) = panel.get_hovered(mouse_context).map(str::to_owned) else { return; };
+
+        let mut proposal = self.proposal.take().unwrap();
+        match button.as_str() {
+            "Accept" => {
+                if let Ok(grid) = proposal.result {
+                    self.edit_active_grid(|active_grid| active_grid.paste(proposal.area.0, &grid));
+                } else {
+                    println!("Nothing to accept, reroll first");
+                    self.proposal = Some(proposal);
+                }
+                return;
+            }
+            "Cancel" => return,
+            "Reroll" => proposal.seed = SeededRng::new(proposal.seed).next_u64(),
+            "Filling: empty cells" | "Filling: everything" => proposal.only_empty = !proposal.only_empty,
+            button if button.starts_with("Generator: ") => proposal.generator = proposal.generator.next(),
+            button if button.starts_with("Walls: ") => proposal.wall = Some(self.icon_server.get_selected_name().to_owned()),
+            button if button.starts_with("Floor: ") => proposal.floor = Some(self.icon_server.get_selected_name().to_owned()),
+            // the tweaks go up a step and wrap back round
+            button => match &mut proposal.generator {
+                Generator::Caves(settings) if button.starts_with("Starting walls: ") => {
+                    settings.fill_chance = if settings.fill_chance > 0.575 { 0.3 } else { settings.fill_chance + 0.05 };
+                }
+                Generator::Caves(settings) if button.starts_with("Smoothing: ") => settings.steps = (settings.steps + 1) % 9,
+                Generator::Dungeon(settings) if button.starts_with("Smallest room: ") => {
+                    settings.min_room = if settings.min_room >= 8 { 2 } else { settings.min_room + 1 };
+                }
+                Generator::Dungeon(settings) if button.starts_with("Smallest split: ") => {
+                    settings.min_leaf = if settings.min_leaf >= 16 { 4 } else { settings.min_leaf + 2 };
+                }
+                _ => {
+                    self.proposal = Some(proposal);
+                    return;
+                }
+            },
+        }
+
+        // wave function collapse gets skipped if there's nothing to learn from
+        while !self.generate_proposal(&mut proposal) {
+            proposal.generator = proposal.generator.next();
+        }
+        self.proposal = Some(proposal);
+    }
+
+    // runs the project's rules over every floor, the panel only opens if something broke one.
+    // a broken rules file just gets printed, it shouldn't get in the way of saving
+    fn lint(&mut self) {
+        let rules = match load_lint_rules(Path::new(LINT_RULES_FILE)) {
+            Ok(Some(rules)) => rules,
+            Ok(None) => return,
+            Err(error) => {
+                println!("Bad lint rules: {error}");
+                return;
+            }
+        };
+
+        let violations = lint_floors(&self.floors, &rules);
+        for violation in violations.iter() {
+            println!("Lint: {}", violation.line());
+        }
+        println!("{} lint violations", violations.len());
+
+        self.lint_violations = if violations.is_empty() { None } else { Some(violations) };
+    }
+
+    fn to_lint_panel(&self, rl: &mut impl CanMeasureText) -> Option<TextPanel> {
+        let violations = self.lint_violations.as_ref()?;
+
+        let mut panel = TextPanel::new_custom(
+            TEXT_SIZE,
+            TEXT_PADDING,
+            INSPECTOR_ITEM_PADDING,
+            STAMP_PANEL_BACKGROUND_COLOR,
+            STAMP_PANEL_TEXT_COLOR,
+            Some(STAMP_PANEL_HOVER_COLOR)
+        );
+
+        panel.add_text_button(&format!("{} lint violations", violations.len()), rl);
+        for violation in violations.iter().take(LINT_PANEL_MAX_LINES) {
+            panel.add_text_button(&violation.line(), rl);
+        }
+        // they all got printed when it was saved, and they're all marked on the grid
+        if violations.len() > LINT_PANEL_MAX_LINES {
+            panel.add_text_button(&format!("+{} more", violations.len() - LINT_PANEL_MAX_LINES), rl);
+        }
+        panel.add_text_button("Close", rl);
+
+        // bottom middle, between the walk check and the stats
+        let rec = panel.as_rec();
+        let position = Vector2::new(
+            (WINDOW_WIDTH as f32 - rec.width) / 2.0,
+            WINDOW_HEIGHT as f32 - rec.height - TEXT_PADDING as f32,
+        );
+        panel.set_position(position);
+
+        return Some(panel);
+    }
+
+    fn update_lint_panel(&mut self, mouse_context: &MouseContext, rl: &mut impl CanMeasureText) {
+        let Some(panel) = self.to_lint_panel(rl) else { return; };
+        if !mouse_context.mouse_left_pressed { return; }
+
+        if panel.get_hovered(mouse_context) == Some("Close") {
+            self.lint_violations = None;
+        }
+    }
+
+    fn update_stats_panel(&mut self, mouse_context: &MouseContext, rl: &mut impl CanMeasureText) {
+        let Some(panel) = self.to_stats_panel(rl) else { return; };
+        if !mouse_context.mouse_left_pressed { return; }
+
+        let stats = tile_stats(self.floors.active_floor().active_grid(), &self.icon_server);
+        let (file, text) = match panel.get_hovered(mouse_context) {
+            Some("Export CSV")  => (STATS_CSV_FILE , stats.to_csv()),
+            Some("Export JSON") => (STATS_JSON_FILE, stats.to_json().pretty(4)),
+            _ => return,
+        };
+
+        println!("Saving stats to {file}");
+        let mut output = fs::File::create(file).expect("File was created");
+        write!(output, "{text}").expect("Write to file");
+    }
+
+    fn update_stamp_name_editor(&mut self, rl: &mut RaylibHandle) {
+        let Some(name) = self.stamp_name_editor.as_mut() else { return; };
+
+        while let Some(c) = rl.get_char_pressed() {
+            name.push(c);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            name.pop();
+        }
+
+        if !rl.is_key_pressed(KeyboardKey::KEY_ENTER) { return; }
+
+        // enter on nothing backs out
+        let name = self.stamp_name_editor.take().unwrap();
+        if name.is_empty() { return; }
+        if !valid_stamp_name(&name) {
+            println!("Can't save a stamp called {name}");
+            return;
+        }
+        let Some((min, max)) = self.active_selection() else { return; };
+
+        let grid = self.floors.active_floor().active_grid().extract(min, max);
+        save_stamp(Path::new(STAMP_PATH), &name, &grid);
+        self.load_stamps();
+    }
+
+    // enter on nothing keeps the name it was given
+    fn update_name_editor(&mut self, rl: &mut RaylibHandle) {
+        let Some((_, name)) = self.name_editor.as_mut() else { return; };
+
+        while let Some(c) = rl.get_char_pressed() {
+            name.push(c);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            name.pop();
+        }
+
+        if !rl.is_key_pressed(KeyboardKey::KEY_ENTER) { return; }
+
+        let (target, name) = self.name_editor.take().unwrap();
+        if name.is_empty() { return; }
+        match target {
+            NameTarget::Region(index) => self.edit_regions(|regions| {
+                if let Some(region) = regions.get_mut(index) { region.name = name; }
+            }),
+            NameTarget::Object(index) => self.edit_objects(|objects| {
+                if let Some(map_object) = objects.get_mut(index) { map_object.name = name; }
+            }),
+            // link names have to be unique, a taken one keeps the name it had
+            NameTarget::Link(index) => {
+                if self.floors.link_name_taken(&name) { return; }
+                let links_before = self.floors.links.clone();
+                let Some(link) = self.floors.links.get_mut(index) else { return; };
+                link.name = name;
+                self.history.record_step(vec![GridEdit::Links { before: links_before, after: self.floors.links.clone() }]);
+            }
+        }
+    }
+
+    // all changes to the active floor's regions go through here, so they can be undone
+    fn edit_regions(&mut self, edit: impl FnOnce(&mut Vec<Region>)) {
+        let floor = self.floors.active_floor_index();
+        let regions = &mut self.floors.active_floor_mut().regions;
+
+        let before = regions.clone();
+        edit(regions);
+        if before != *regions {
+            self.history.record_step(vec![GridEdit::Regions { floor, before, after: regions.clone() }]);
+        }
+    }
+
+    // grab a corner to move it, drag anywhere else to make a new one. right click removes one
+    fn update_region(&mut self, id: Option<usize>, mouse_context: &MouseContext) {
+        let floor = self.floors.active_floor_index();
+        let pos = id.map(|id| index_to_pos(id, self.floors.active_floor().size()));
+
+        if self.region_drag.as_ref().is_some_and(|drag| drag.floor != floor) { self.region_drag = None }
+
+        if let Some(drag) = self.region_drag.as_ref() {
+            if let Some(pos) = pos {
+                let fixed = drag.fixed;
+                self.floors.active_floor_mut().regions[drag.index].set_corners(fixed, pos);
+            }
+            if !mouse_context.mouse_left_down { self.finish_region_drag() }
+            return;
+        }
+
+        let Some(pos) = pos else { return; };
+        let regions = &self.floors.active_floor().regions;
+
+        if mouse_context.mouse_right_pressed {
+            // the one on top goes first
+            let Some(index) = regions.iter().rposition(|region| region.contains(pos)) else { return; };
+            self.edit_regions(|regions| { regions.remove(index); });
+            return;
+        }
+        if !mouse_context.mouse_left_pressed { return; }
+
+        let before = regions.clone();
+        let corner = regions
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, region)| region.corners().contains(&pos).then(|| (index, region.opposite_corner(pos))));
+
+        let (index, fixed, is_new) = match corner {
+            Some((index, fixed)) => (index, fixed, false),
+            None => {
+                let color = REGION_COLORS[before.len() % REGION_COLORS.len()];
+                let name = format!("region {}", before.len() + 1);
+                self.floors.active_floor_mut().regions.push(Region::new(&name, (color.r, color.g, color.b), pos, pos));
+                (before.len(), pos, true)
+            }
+        };
+        self.region_drag = Some(RegionDrag { floor, index, fixed, before, is_new });
+    }
+
+    fn cancel_region_drag(&mut self) {
+        let Some(drag) = self.region_drag.take() else { return; };
+        self.floors.floor_mut(drag.floor).regions = drag.before;
+    }
+
+    // the drag was already applied, this just makes it one step
+    fn finish_region_drag(&mut self) {
+        let Some(drag) = self.region_drag.take() else { return; };
+        let after = self.floors.floor_mut(drag.floor).regions.clone();
+        if drag.before == after { return; }
+
+        if drag.is_new {
+            self.name_editor = Some((NameTarget::Region(drag.index), String::new()));
+        }
+        self.history.record_step(vec![GridEdit::Regions { floor: drag.floor, before: drag.before, after }]);
+    }
+
+    // all changes to the active floor's objects go through here, so they can be undone
+    fn edit_objects(&mut self, edit: impl FnOnce(&mut Vec<MapObject>)) {
+        let floor = self.floors.active_floor_index();
+        let objects = &mut self.floors.active_floor_mut().objects;
+
+        let before = objects.clone();
+        edit(objects);
+        if before != *objects {
+            self.history.record_step(vec![GridEdit::Objects { floor, before, after: objects.clone() }]);
+        }
+    }
+
+    // the selected object, if it's on the floor you're looking at
+    fn active_object(&self) -> Option<usize> {
+        let (floor, index) = self.selected_object?;
+        if floor != self.floors.active_floor_index() { return None; }
+        if index >= self.floors.active_floor().objects.len() { return None; }
+        return Some(index);
+    }
+
+    // every object on the active floor as something the mouse can grab, in draw order
+    fn to_object_panels<T: DrawableObject>(&self, grid_panel: &GridPanel<T>) -> Vec<ImagePanel<'_>> {
+        let selected = self.active_object();
+        let dragging = self.object_drag.as_ref().map(|drag| drag.index);
+
+        self.floors.active_floor().objects
+            .iter()
+            .enumerate()
+            .map(|(index, map_object)| {
+                let center = point_to_screen(grid_panel, map_object.position);
+                let drag_context = PanelUiDragContext {
+                    is_draggable: true,
+                    position: center - Vector2::new(OBJECT_SIZE / 2.0, OBJECT_SIZE / 2.0),
+                    is_dragging: dragging == Some(index),
+                };
+                let texture = self.icon_server.get_by_name(&map_object.icon).and_then(|image_container| image_container.texture.as_ref());
+                let highlight = if selected == Some(index) { Some(OBJECT_SELECTED_COLOR) } else { None };
+
+                ImagePanel::new_custom(drag_context, texture, OBJECT_SIZE, highlight)
+            })
+            .collect()
+    }
+
+    // click an object to select it and drag it around, click the grid to place a new one.
+    // right click removes one
+    fn update_objects(&mut self, id: Option<usize>, mouse_context: &MouseContext) {
+        let floor = self.floors.active_floor_index();
+        if self.object_drag.as_ref().is_some_and(|drag| drag.floor != floor) { self.finish_object_drag() }
+
+        let grid_panel = self.to_grid_panel();
+        let panels = self.to_object_panels(&grid_panel);
+        let hovered = panels.iter().rposition(|panel| panel.mouse_over_panel(mouse_context));
+
+        // the main loop ends the drag when the button comes up, this just moves it
+        if let Some(index) = self.object_drag.as_ref().map(|drag| drag.index) {
+            let drag_context = panels[index].do_dragging_at(mouse_context, panels[index].get_drag_context());
+            let center = drag_context.position + Vector2::new(OBJECT_SIZE / 2.0, OBJECT_SIZE / 2.0);
+            let point = screen_to_point(&grid_panel, center);
+            drop(panels);
+
+            // it can go right up to the edge, but not off it
+            let (rows, cols) = self.floors.active_floor().size();
+            let position = (point.0.clamp(0.0, cols as f32), point.1.clamp(0.0, rows as f32));
+            self.floors.active_floor_mut().objects[index].position = position;
+
+            return;
+        }
+
+        let point = screen_to_point(&grid_panel, mouse_context.mouse_pos);
+        drop(panels);
+
+        if mouse_context.mouse_right_pressed {
+            let Some(index) = hovered else { return; };
+            self.selected_object = None;
+            self.edit_objects(|objects| { objects.remove(index); });
+            return;
+        }
+        if !mouse_context.mouse_left_pressed { return; }
+
+        if let Some(index) = hovered {
+            self.selected_object = Some((floor, index));
+            let before = self.floors.active_floor().objects.clone();
+            self.object_drag = Some(ObjectDrag { floor, index, before });
+            return;
+        }
+
+        // off the grid doesn't place anything
+        if id.is_none() { return; }
+
+        let index = self.floors.active_floor().objects.len();
+        let name = format!("object {}", index + 1);
+        let icon = self.icon_server.get_selected_name().to_string();
+        self.edit_objects(|objects| objects.push(MapObject::new(&name, &icon, point)));
+
+        self.selected_object = Some((floor, index));
+        self.name_editor = Some((NameTarget::Object(index), String::new()));
+    }
+
+    fn cancel_object_drag(&mut self) {
+        let Some(drag) = self.object_drag.take() else { return; };
+        self.floors.floor_mut(drag.floor).objects = drag.before;
+    }
+
+    // the drag was already applied, this just makes it one step
+    fn finish_object_drag(&mut self) {
+        let Some(drag) = self.object_drag.take() else { return; };
+        let after = self.floors.floor_mut(drag.floor).objects.clone();
+        if drag.before == after { return; }
+
+        self.history.record_step(vec![GridEdit::Objects { floor: drag.floor, before: drag.before, after }]);
+    }
+
+    fn delete_selected_object(&mut self) {
+        let Some(index) = self.active_object() else { return; };
+        self.finish_object_drag();
+        self.selected_object = None;
+        self.edit_objects(|objects| { objects.remove(index); });
+    }
+
+    fn update_shape(&mut self, shape: Shape, id: Option<usize>, mouse_context: &MouseContext) {
+        let pos = id.map(|id| index_to_pos(id, self.floors.active_floor().size()));
+
+        if let (Some(pos), None) = (pos, &self.shape_drag) {
+            if mouse_context.mouse_left_pressed || mouse_context.mouse_right_pressed {
+                self.shape_drag = Some(ShapeDrag { shape, start: pos, end: pos, erase: !mouse_context.mouse_left_pressed });
+            }
+        }
+
+        let Some(drag) = self.shape_drag.as_mut() else { return; };
+
+        // off the grid, the shape stays where it was last
+        if let Some(pos) = pos { drag.end = pos; }
+
+        let still_down = if drag.erase { mouse_context.mouse_right_down } else { mouse_context.mouse_left_down };
+        if still_down { return; }
+
+        let drag = self.shape_drag.take().expect("Checked above");
+        let item = if drag.erase { None } else { Some(self.icon_server.get_selected_name().to_string()) };
+        let orientation = if drag.erase { Orientation::default() } else { self.brush_orientation };
+        let cells = drag.shape.cells(drag.start, drag.end);
+        self.edit_active_cells(&cells, |grid| {
+            for pos in cells.iter() {
+                grid.set_oriented(*pos, item.clone(), orientation);
+            }
+        });
+    }
+
+    fn update_brush(&mut self, id: Option<usize>, mouse_context: &MouseContext) {
+        // the whole stroke is one undo step
+        if !mouse_context.mouse_left_down && !mouse_context.mouse_right_down {
+            self.painting = false;
+            self.history.end_step();
+        }
+        if id.is_some() && (mouse_context.mouse_left_pressed || mouse_context.mouse_right_pressed) {
+            self.painting = true;
+            self.history.begin_step();
+        }
+
+        let Some(id) = id else { return; };
+        if !self.painting { return; }
+
+        let pos = index_to_pos(id, self.floors.active_floor().size());
+
+        if let EditorTool::Terrain(index) = self.tool {
+            let terrain = self.terrains[index].clone();
+            let place = mouse_context.mouse_left_down;
+            // paint only ever touches the cell and the ones around it
+            let mut cells = self.floors.active_floor().active_grid().neighbors(pos, Connectivity::Eight);
+            cells.push(pos);
+            self.edit_active_cells(&cells, |grid| terrain.paint(grid, pos, place));
+            return;
+        }
+
+        let item = if mouse_context.mouse_left_down {
+            Some(self.icon_server.get_selected_name().to_string())
+        } else {
+            None
+        };
+        let orientation = if item.is_some() { self.brush_orientation } else { Orientation::default() };
+        self.edit_active_cells(&[pos], |grid| grid.set_oriented(pos, item, orientation));
+    }
+
+    fn active_target(&self) -> GridTarget {
+        GridTarget {
+            floor: self.floors.active_floor_index(),
+            layer: self.floors.active_floor().active_layer_index(),
+        }
+    }
+
+    // all changes to the grid you're looking at go through here, so they can be undone
+    fn edit_active_grid(&mut self, edit: impl FnOnce(&mut TileGrid<String>)) {
+        self.edit_grid(self.active_target(), edit);
+    }
+
+    fn edit_grid(&mut self, target: GridTarget, edit: impl FnOnce(&mut TileGrid<String>)) {
+        let grid = self.floors.floor_mut(target.floor).layer_grid_mut(target.layer);
+
+        let before = grid.clone();
+        edit(grid);
+        self.history.record_grid_changes(target, &before, grid);
+    }
+
+    // like edit_active_grid, but only the cells the edit can touch get copied and compared.
+    // the brush goes through here every frame it's held down, the whole grid would add up on big maps
+    fn edit_active_cells(&mut self, cells: &[(usize, usize)], edit: impl FnOnce(&mut TileGrid<String>)) {
+        let target = self.active_target();
+        let grid = self.floors.floor_mut(target.floor).layer_grid_mut(target.layer);
+
+        let before = cells.iter().map(|pos| (*pos, grid.get_tile(*pos).clone())).collect();
+        edit(grid);
+        self.history.record_cell_changes(target, before, grid);
+    }
+
+    fn resize_active_floor(&mut self, new_rows: usize, new_cols: usize, anchor: Anchor) {
+        if !self.is_world_floor() {
+            self.edit_active_floor(|floors| floors.resize_active_floor(new_rows, new_cols, anchor));
+            return;
+        }
+
+        // the window grows into the world around it instead of adding empty cells,
+        // which can't be undone like a normal resize
+        self.finish_region_drag();
+        self.finish_object_drag();
+        self.sync_world();
+        let edges = EdgeDeltas::anchored(self.floors.active_floor().size(), (new_rows, new_cols), anchor);
+        self.floors.resize_active_floor(new_rows, new_cols, anchor);
+
+        let view = self.world.as_mut().unwrap();
+        view.origin = (view.origin.0 - edges.left, view.origin.1 - edges.top);
+        self.show_world();
+    }
+
+    fn is_world_floor(&self) -> bool {
+        self.world.as_ref().is_some_and(|view| view.floor == self.floors.active_floor_index())
+    }
+
+    fn toggle_world(&mut self) {
+        self.cancel_floating();
+        if self.world.is_some() {
+            self.leave_world();
+            return;
+        }
+
+        let floor = self.floors.active_floor_index();
+        let (world, origin) = match fs::File::open(WORLD_FILE) {
+            Ok(mut input) => {
+                let mut buffer = String::new();
+                input.read_to_string(&mut buffer).expect("Read to buffer");
+                let Some(mut world) = json::parse(&buffer).ok().and_then(|source| ChunkedWorld::from_json(&source)) else {
+                    println!("World file is broken, not loading it");
+                    return;
+                };
+                world.remove_empty_chunks();
+                // the window starts on the top left of whatever's been painted
+                let origin = world.bounds().map(|(min, _)| min).unwrap_or((0, 0));
+                (world, origin)
+            }
+            // a new world starts out as the ground layer you were working on
+            Err(_) => {
+                let mut world = ChunkedWorld::new();
+                world.paste_grid((0, 0), &self.floors.active_floor().layers()[0].grid);
+                (world, (0, 0))
+            }
+        };
+
+        println!("Editing the world, {} chunks", world.chunk_count());
+        self.world = Some(WorldView { world, origin, floor });
+        self.show_world();
+    }
+
+    // saves the world first, the floor keeps whatever the window was showing
+    fn leave_world(&mut self) {
+        if self.world.is_none() { return; }
+        self.save_world();
+        self.world = None;
+        println!("Left the world");
+    }
+
+    // writes the window back into the world, a half done move goes home first
+    fn sync_world(&mut self) {
+        if self.world.is_none() { return; }
+        self.cancel_floating();
+        let Some(view) = self.world.as_mut() else { return; };
+        view.world.paste_grid(view.origin, &self.floors.floors()[view.floor].layers()[0].grid);
+    }
+
+    // cuts the window out of the world again. the history, selection, ect. were all about the old window
+    fn show_world(&mut self) {
+        let Some(view) = self.world.as_ref() else { return; };
+        let (rows, cols) = self.floors.floors()[view.floor].size();
+        *self.floors.floor_mut(view.floor).layer_grid_mut(0) = view.world.to_grid(view.origin, rows, cols);
+
+        self.history.clear();
+        self.selection = None;
+        self.floating = None;
+        self.shape_drag = None;
+        self.proposal = None;
+    }
+
+    fn scroll_world(&mut self, dx: i64, dy: i64) {
+        if self.world.is_none() { return; }
+        self.finish_region_drag();
+        self.finish_object_drag();
+        self.sync_world();
+
+        let view = self.world.as_mut().unwrap();
+        view.origin = (view.origin.0 + dx, view.origin.1 + dy);
+        self.show_world();
+    }
+
+    // only the chunks with something in them get written
+    fn save_world(&mut self) {
+        self.sync_world();
+        let Some(view) = self.world.as_ref() else { return; };
+
+        let mut output = fs::File::create(WORLD_FILE).expect("File was created");
+        write!(output, "{}", view.world.to_json()).expect("Write to file");
+    }
+
+    // for changes to the whole floor, the size and links can change too
+    fn edit_active_floor(&mut self, edit: impl FnOnce(&mut FloorStack<String>)) {
+        // the region or object being dragged might not make it through
+        self.finish_region_drag();
+        self.finish_object_drag();
+
+        let floor = self.floors.active_floor_index();
+        let before = self.floors.active_floor().clone();
+        let links_before = self.floors.links.clone();
+
+        edit(&mut self.floors);
+
+        let mut edits = vec![GridEdit::Floor { floor, before, after: self.floors.active_floor().clone() }];
+        if links_before != self.floors.links {
+            edits.push(GridEdit::Links { before: links_before, after: self.floors.links.clone() });
+        }
+        self.history.record_step(edits);
+    }
+
+    // just the selection on the active layer if there is one, otherwise the whole floor
+    fn transform(&mut self, transform: GridTransform) {
+        // turning a hex map a quarter turn doesn't make hexes
+        if self.floors.active_floor().topology != Topology::Square {
+            println!("Can't rotate or flip hex maps");
+            return;
+        }
+
+        let Some((min, max)) = self.active_selection() else {
+            if self.is_world_floor() {
+                println!("Can't turn the whole world, select an area first");
+                return;
+            }
+            self.edit_active_floor(|floors| floors.transform_active_floor(transform));
+            return;
+        };
+
+        // a turned selection that doesn't fit anymore gets cut off at the edge
+        let grid = self.floors.active_floor().active_grid().extract(min, max).transformed(transform);
+        self.edit_active_grid(|active_grid| {
+            active_grid.clear_area(min, max);
+            active_grid.paste(min, &grid);
+        });
+
+        let (rows, cols) = self.floors.active_floor().size();
+        let end = ((min.0 + grid.cols - 1).min(cols - 1), (min.1 + grid.rows - 1).min(rows - 1));
+        self.selection = Some((min, end));
+    }
+
+    // square, then each kind of hex. the cells stay where they are in the grid
+    fn cycle_topology(&mut self) {
+        self.edit_active_floor(|floors| {
+            let floor = floors.active_floor_mut();
+            floor.topology = floor.topology.next();
+            // hexes are only drawn top down
+            if floor.topology != Topology::Square { floor.projection = Projection::TopDown }
+        });
+    }
+
+    // the cells don't move in the grid, only how they're drawn
+    fn cycle_projection(&mut self) {
+        if self.floors.active_floor().topology != Topology::Square {
+            println!("Isometric only works on square maps");
+            return;
+        }
+        self.edit_active_floor(|floors| {
+            let floor = floors.active_floor_mut();
+            floor.projection = floor.projection.next();
+        });
+    }
+
+    fn insert_floor_above(&mut self) {
+        // the world's floor would get pushed up out from under it
+        if self.world.is_some() {
+            println!("Can't add floors while editing the world");
+            return;
+        }
+        let (rows, cols) = self.floors.active_floor().size();
+        let index = self.floors.active_floor_index() + 1;
+        let mut floor = TileMap::new(rows, cols, &DEFAULT_LAYER_NAMES);
+        floor.topology = self.floors.active_floor().topology;
+        floor.projection = self.floors.active_floor().projection;
+
+        self.floors.insert_floor(index, floor.clone());
+        self.history.record_step(vec![GridEdit::InsertFloor { index, floor }]);
+    }
+
+    fn undo(&mut self) {
+        // a half done move is part of the step being undone
+        self.floating = None;
+        self.cancel_region_drag();
+        self.cancel_object_drag();
+        if self.history.undo(&mut self.floors) {
+            // the floor it was waiting on might not be there anymore
+            self.pending_link = None;
+        }
+    }
+
+    fn redo(&mut self) {
+        self.floating = None;
+        self.cancel_region_drag();
+        self.cancel_object_drag();
+        if self.history.redo(&mut self.floors) {
+            self.pending_link = None;
+        }
+    }
+
+    // first press picks one end, second press (on any floor) makes the link
+    fn update_links(&mut self, mouse_context: &MouseContext, link_key_pressed: bool) {
+        if !link_key_pressed { return; }
+
+        let grid_panel = self.to_grid_panel();
+        let Some(id) = grid_panel.get_hovered_id(mouse_context) else { return; };
+
+        let end = LinkEnd {
+            floor: self.floors.active_floor_index(),
+            pos: index_to_pos(id, self.floors.active_floor().size()),
+        };
+
+        if let Some(from) = self.pending_link.take() {
+            if from == end { return; }
+            let links_before = self.floors.links.clone();
+            let name = self.floors.unused_link_name();
+            self.floors.add_link(&name, from, end);
+            self.history.record_step(vec![GridEdit::Links { before: links_before, after: self.floors.links.clone() }]);
+            self.name_editor = Some((NameTarget::Link(self.floors.links.len() - 1), String::new()));
+        } else {
+            self.pending_link = Some(end);
+        }
+    }
+
+    fn draw_links(&self, d: &mut RaylibDrawHandle, grid_panel: &GridPanel<Vec<OrientedTexture>>) {
+        let floor = self.floors.active_floor_index();
+        let size = self.floors.active_floor().size();
+
+        let mut ends: Vec<(&str, LinkEnd, Color)> = self.floors
+            .links_on_floor(floor)
+            .into_iter()
+            .map(|(link, end)| (link.name.as_str(), end, LINK_MARKER_COLOR))
+            .collect();
+        if let Some(end) = self.pending_link.filter(|end| end.floor == floor) {
+            ends.push(("?", end, LINK_PENDING_COLOR));
+        }
+
+        for (name, end, color) in ends {
+            let rec = grid_panel.rec_of_item(pos_to_index(end.pos, size));
+            let center = Vector2::new(rec.x + rec.width / 2.0, rec.y + rec.height / 2.0);
+            d.draw_circle_v(center, rec.width / 6.0, color);
+            d.draw_text(name, rec.x as i32, rec.y as i32, TEXT_SIZE / 2, color);
+        }
+    }
+
+    // see through boxes over the cells, with handles on the corners while the region tool is out
+    fn draw_regions(&self, d: &mut RaylibDrawHandle, grid_panel: &GridPanel<Vec<OrientedTexture>>) {
+        let size = self.floors.active_floor().size();
+
+        for region in self.floors.active_floor().regions.iter() {
+            let (r, g, b) = region.color;
+
+            if grid_panel.iso_layout().is_some() {
+                // a big diamond, from the top corner of min around to the left corner
+                let (min_x, min_y) = (region.min.0 as f32, region.min.1 as f32);
+                let (max_x, max_y) = (region.max.0 as f32 + 1.0, region.max.1 as f32 + 1.0);
+                let [top, right, bottom, left] = [(min_x, min_y), (max_x, min_y), (max_x, max_y), (min_x, max_y)]
+                    .map(|point| point_to_screen(grid_panel, point));
+
+                d.draw_triangle(top, left, bottom, Color::new(r, g, b, REGION_FILL_ALPHA));
+                d.draw_triangle(top, bottom, right, Color::new(r, g, b, REGION_FILL_ALPHA));
+                for (start, end) in [(top, right), (right, bottom), (bottom, left), (left, top)] {
+                    d.draw_line_ex(start, end, 2.0, Color::new(r, g, b, 255));
+                }
+                d.draw_text(&region.name, top.x as i32 + 4, top.y as i32 + 4, TEXT_SIZE / 2, Color::new(r, g, b, 255));
+            } else {
+                let min_rec = grid_panel.rec_of_item(pos_to_index(region.min, size));
+                let max_rec = grid_panel.rec_of_item(pos_to_index(region.max, size));
+                let rec = Rectangle::new(min_rec.x, min_rec.y, max_rec.x + max_rec.width - min_rec.x, max_rec.y + max_rec.height - min_rec.y);
+
+                d.draw_rectangle_rec(rec, Color::new(r, g, b, REGION_FILL_ALPHA));
+                d.draw_rectangle_lines_ex(rec, 2.0, Color::new(r, g, b, 255));
+                d.draw_text(&region.name, rec.x as i32 + 4, rec.y as i32 + 4, TEXT_SIZE / 2, Color::new(r, g, b, 255));
+            }
+
+            if self.tool != EditorTool::Region { continue; }
+            for corner in region.corners() {
+                let center = point_to_screen(grid_panel, (corner.0 as f32 + 0.5, corner.1 as f32 + 0.5));
+                let handle = Rectangle::new(center.x - 4.0, center.y - 4.0, 8.0, 8.0);
+                d.draw_rectangle_rec(handle, Color::new(r, g, b, 255));
+            }
+        }
+    }
+
+    // on top of everything on the floor, with their names under them
+    fn draw_objects(&self, d: &mut RaylibDrawHandle, grid_panel: &GridPanel<Vec<OrientedTexture>>) {
+        let panels = self.to_object_panels(grid_panel);
+
+        for (panel, map_object) in panels.iter().zip(self.floors.active_floor().objects.iter()) {
+            panel.draw_panel(d, &MouseContext::inactive());
+
+            let rec = panel.as_rec();
+            d.draw_text(&map_object.name, rec.x as i32, (rec.y + rec.height) as i32 + 2, TEXT_SIZE / 2, OBJECT_TEXT_COLOR);
+        }
+    }
+
+    // opens the note editor on the hovered cell
+    fn update_notes(&mut self, mouse_context: &MouseContext, note_key_pressed: bool) {
+        if !note_key_pressed { return; }
+
+        let grid_panel = self.to_grid_panel();
+        let Some(id) = grid_panel.get_hovered_id(mouse_context) else { return; };
+
+        let grid = self.floors.active_floor().active_grid();
+        let pos = index_to_pos(id, grid.size());
+        let text = grid.get_note(pos).unwrap_or_default().to_owned();
+
+        self.note_editor = Some(NoteEditor { pos, text });
+    }
+
+    // replaces the hovered cell's tile with the selected one everywhere on the active layer,
+    // or on every layer of the floor. a selection keeps it inside the box
+    fn update_replace(&mut self, mouse_context: &MouseContext, replace_key_pressed: bool, all_layers: bool) {
+        if !replace_key_pressed { return; }
+
+        let grid_panel = self.to_grid_panel();
+        let Some(id) = grid_panel.get_hovered_id(mouse_context) else { return; };
+
+        let Some(find) = self.floors.active_floor().active_grid().get_from_index(id).clone() else { return; };
+        let replace = self.icon_server.get_selected_name().to_string();
+        let area = self.active_selection();
+
+        if !all_layers {
+            self.edit_active_grid(|grid| { grid.replace_all(&find, &replace, area); });
+            return;
+        }
+
+        self.edit_active_floor(|floors| {
+            let floor = floors.active_floor_mut();
+            for index in 0..floor.layers().len() {
+                floor.layer_grid_mut(index).replace_all(&find, &replace, area);
+            }
+        });
+    }
+
+    // enter saves the note, saving an empty note removes it
+    fn update_note_editor(&mut self, rl: &mut RaylibHandle) {
+        let Some(note_editor) = self.note_editor.as_mut() else { return; };
+
+        while let Some(c) = rl.get_char_pressed() {
+            note_editor.text.push(c);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            note_editor.text.pop();
+        }
+
+        if rl.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            let note_editor = self.note_editor.take().unwrap();
+            self.edit_active_cells(&[note_editor.pos], |grid| grid.set_note(note_editor.pos, Some(note_editor.text)));
+        }
+    }
+
+    fn draw_notes(&self, d: &mut RaylibDrawHandle, grid_panel: &GridPanel<Vec<OrientedTexture>>, mouse_context: &MouseContext) {
+        let map = self.floors.active_floor();
+        let (rows, cols) = map.size();
+
+        // little flag in the top right corner of any cell with a note, on any layer
+        for i in 0..rows*cols {
+            let pos = index_to_pos(i, map.size());
+            if map.layers().iter().all(|layer| layer.grid.get_note(pos).is_none()) { continue; }
+
+            let rec = grid_panel.rec_of_item(i);
+            let marker_size = rec.width / 4.0;
+            d.draw_triangle(
+                Vector2::new(rec.x + rec.width - marker_size, rec.y),
+                Vector2::new(rec.x + rec.width, rec.y + marker_size),
+                Vector2::new(rec.x + rec.width, rec.y),
+                NOTE_MARKER_COLOR,
+            );
+        }
+
+        let lines: Vec<String> = if let Some(note_editor) = &self.note_editor {
+            vec![format!("Note: {}_", note_editor.text)]
+        } else if let Some(id) = grid_panel.get_hovered_id(mouse_context) {
+            let pos = index_to_pos(id, map.size());
+            map.layers()
+                .iter()
+                .filter_map(|layer| layer.grid.get_note(pos).map(|note| format!("{}: {note}", layer.name)))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        draw_text_box(d, &lines, mouse_context.mouse_pos);
+    }
+
+    pub fn update_pallet(&mut self, mouse_context: &MouseContext) {
+        let pallet_panel = self.to_pallet_panel();
+
+        let Some(id) = pallet_panel.get_hovered_id(&mouse_context) else { return; };
+
+        let name = self.icon_server.assets[id].0.clone();
+
+        if mouse_context.mouse_left_pressed {
+            self.icon_server.set_selected_by_name(&name);
+        }
+        if mouse_context.mouse_right_pressed {
+            self.icon_server.set_default_by_name(&name);
+        }
+    }
+
+    fn load_definitions(&mut self, dir: &Path) {
+        let (definitions, errors) = load_palette_manifest(dir);
+        for error in errors { println!("Bad palette manifest: {error}"); }
+        for (name, definition) in definitions {
+            self.icon_server.set_definition(&name, definition);
+        }
+
+        // loading a folder again replaces its sets
+        for terrain in load_terrain_sets(dir) {
+            if let Some(tile) = terrain.tiles.values().find(|tile| self.icon_server.get_by_name(tile).is_none()) {
+                println!("Terrain {} uses {tile}, which isn't loaded. Skipping it", terrain.name);
+                continue;
+            }
+
+            let missing = terrain.missing_masks();
+            if !missing.is_empty() {
+                println!("Terrain {} has no tiles for masks {missing:?}, those cells are left alone", terrain.name);
+            }
+
+            match self.terrains.iter_mut().find(|existing| existing.name == terrain.name) {
+                Some(existing) => *existing = terrain,
+                None => self.terrains.push(terrain),
+            }
+        }
+    }
+
+    fn tool_name(&self) -> String {
+        match self.tool {
+            EditorTool::Terrain(index) => format!("Terrain ({})", self.terrains[index].name),
+            tool => tool.name(),
+        }
+    }
+
+    // one manifest per icon folder, icons without a definition get a blank one to fill in
+    fn save_manifests(&self) {
+        let mut folders: BTreeMap<&Path, Vec<(String, TileDefinition)>> = BTreeMap::new();
+
+        for (name, _) in self.icon_server.assets.iter() {
+            let path = Path::new(name);
+            let dir = path.parent().expect("Icon is in a folder");
+            let file_name = path.file_name().expect("Icon has a file name").to_str().expect("Valid path");
+
+            let definition = self.icon_server.get_definition(name).cloned().unwrap_or_default();
+            folders.entry(dir).or_default().push((file_name.to_owned(), definition));
+        }
+
+        for (dir, definitions) in folders {
+            save_palette_manifest(dir, &definitions);
+        }
+
+        // terrain sets go in the folder their tiles are in
+        let mut terrain_folders: BTreeMap<&Path, Vec<&TerrainSet>> = BTreeMap::new();
+        for terrain in self.terrains.iter() {
+            let Some(tile) = terrain.tiles.values().next() else { continue; };
+            let dir = Path::new(tile).parent().expect("Icon is in a folder");
+            terrain_folders.entry(dir).or_default().push(terrain);
+        }
+
+        for (dir, terrains) in terrain_folders {
+            save_terrain_sets(dir, &terrains);
+        }
+    }
+
+    // the map, plus the definitions of every tile it uses. games read the
+    // properties straight out of here, so they don't need the manifest
+    fn to_save_json(&self) -> json::JsonValue {
+        let mut json_object = self.floors.to_json();
+
+        let mut used_names = BTreeSet::new();
+        for floor in self.floors.floors() {
+            for layer in floor.layers() {
+                used_names.extend(layer.grid.items());
+            }
+            used_names.extend(floor.objects.iter().map(|map_object| &map_object.icon));
+        }
+
+        json_object["palette"] = json::object! {};
+        for name in used_names {
+            if let Some(definition) = self.icon_server.get_definition(name) {
+                json_object["palette"][name.as_str()] = definition.to_json();
+            }
+        }
+
+        return json_object;
+    }
+
+    // the manifest wins, only fill in the tiles it doesn't know about
+    fn load_save_definitions(&mut self, source: &json::JsonValue) {
+        for (name, value) in source["palette"].entries() {
+            if self.icon_server.get_definition(name).is_some() { continue; }
+            let Some(definition) = TileDefinition::from_json(value) else {
+                println!("Save has a broken definition for {name}, skipping it");
+                continue;
+            };
+            self.icon_server.set_definition(name, definition);
+        }
+    }
+
+    fn to_inspector_panel(&self, mouse_context: &MouseContext, rl: &mut impl CanMeasureText) -> Option<TextPanel> {
+        let pallet_panel = self.to_pallet_panel();
+        let id = pallet_panel.get_hovered_id(mouse_context)?;
+        let name = &self.icon_server.assets[id].0;
+
+        let mut panel = TextPanel::new_custom(
+            TEXT_SIZE,
+            TEXT_PADDING,
+            INSPECTOR_ITEM_PADDING,
+            INSPECTOR_BACKGROUND_COLOR,
+            INSPECTOR_TEXT_COLOR,
+            None
+        );
+
+        let mut lines = vec![name.clone()];
+        if let Some(definition) = self.icon_server.get_definition(name) {
+            if !definition.description.is_empty() { lines.push(definition.description.clone()); }
+            if !definition.tags.is_empty() { lines.push(format!("tags: {}", definition.tags.join(", "))); }
+            for (property_name, property) in definition.properties.iter() {
+                lines.push(format!("{property_name}: {property}"));
+            }
+        } else {
+            lines.push("no definition".to_owned());
+        }
+
+        for line in lines {
+            panel.add_text_button(&line, rl);
+        }
+
+        panel.set_position(mouse_context.mouse_pos + Vector2::new(TEXT_PADDING as f32, TEXT_PADDING as f32));
+        return Some(panel);
     }
 
     // returns textures_dirty
@@ -317,8 +2483,10 @@ impl<'a> GridHandler {
             // TODO: this should be simpler
         if path.is_dir() {
             self.icon_server.load_icons(&mut get_images_from_path(&path));
+            self.load_definitions(&path);
         } else {
             self.icon_server.load_icon(get_image_from_path(&path));
+            self.load_definitions(path.parent().expect("Image is in a folder"));
         }
         return true; // textures_dirty = true; // Remember to call when adding images
     }
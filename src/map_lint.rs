@@ -0,0 +1,366 @@
+// project rules a map has to follow, like "lava never touches grass" or "the border is all walls".
+// they live in a json file so each project can have its own, and get checked on every save
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use json::{object, JsonValue};
+
+use crate::tile_grid::*;
+use crate::tile_map::*;
+use crate::floor_stack::FloorStack;
+use crate::map_merge::read_json_file;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintCheck {
+    // tile next to other, neighbours are the same ones a fill spreads to
+    NeverTouch { tile: String, other: String },
+    // every cell along the edge is tile
+    Border { tile: String },
+    // how many cells hold tile, either end can be left open
+    Count { tile: String, min: Option<usize>, max: Option<usize> },
+    NoEmpty,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintRule {
+    pub name: String,
+    // which layer of a map it looks at, the ground layer if it doesn't say.
+    // plain grids don't have layers, so only rules without one apply to them
+    pub layer: Option<String>,
+    pub check: LintCheck,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintViolation {
+    pub rule: String,
+    // None when it's a plain grid
+    pub floor: Option<usize>,
+    pub layer: Option<String>,
+    // None when it's about the whole grid, like too few of something
+    pub pos: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl ToAndFromJsonValue for LintRule {
+    fn to_json(&self) -> JsonValue {
+        let mut json_object = match &self.check {
+            LintCheck::NeverTouch { tile, other } => object! { kind: "never_touch", tile: tile.clone(), other: other.clone() },
+            LintCheck::Border { tile } => object! { kind: "border", tile: tile.clone() },
+            LintCheck::Count { tile, min, max } => object! { kind: "count", tile: tile.clone(), min: *min, max: *max },
+            LintCheck::NoEmpty => object! { kind: "no_empty" },
+        };
+        json_object["name"] = self.name.clone().into();
+        if let Some(layer) = self.layer.as_ref() {
+            json_object["layer"] = layer.clone().into();
+        }
+        return json_object;
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        let tile = || source["tile"].as_str().map(str::to_owned);
+        let check = match source["kind"].as_str()? {
+            "never_touch" => LintCheck::NeverTouch { tile: tile()?, other: source["other"].as_str()?.to_owned() },
+            "border"      => LintCheck::Border { tile: tile()? },
+            "count"       => LintCheck::Count { tile: tile()?, min: source["min"].as_usize(), max: source["max"].as_usize() },
+            "no_empty"    => LintCheck::NoEmpty,
+            _ => return None,
+        };
+
+        Some(LintRule {
+            // the kind will do if it isn't named
+            name: source["name"].as_str().or(source["kind"].as_str())?.to_owned(),
+            layer: source["layer"].as_str().map(str::to_owned),
+            check,
+        })
+    }
+}
+
+// Returns Ok(None) if there's no rules file, so projects without one don't get nagged.
+// a rules file with a mistake in it is an Err, none of its rules get used
+pub fn load_lint_rules(path: &Path) -> Result<Option<Vec<LintRule>>, String> {
+    let Ok(mut input) = fs::File::open(path) else { return Ok(None); };
+
+    let mut buffer = String::new();
+    input.read_to_string(&mut buffer).map_err(|_| format!("{}: can't read it", path.display()))?;
+    let source = json::parse(&buffer).map_err(|error| format!("{}: {error}", path.display()))?;
+
+    if source["version"] != "1.0" {
+        return Err(format!("{}: unknown version {}", path.display(), source["version"]));
+    }
+
+    let mut rules = vec![];
+    for (index, rule_json) in source["rules"].members().enumerate() {
+        let rule = LintRule::from_json(rule_json)
+            .ok_or_else(|| format!("{}: rule {} isn't valid, kind is {}", path.display(), index + 1, rule_json["kind"]))?;
+        rules.push(rule);
+    }
+    return Ok(Some(rules));
+}
+
+fn grid_violation(rule: &LintRule, pos: Option<(usize, usize)>, message: String) -> LintViolation {
+    LintViolation { rule: rule.name.clone(), floor: None, layer: None, pos, message }
+}
+
+fn check_grid(grid: &TileGrid<String>, rule: &LintRule, connectivity: Connectivity) -> Vec<LintViolation> {
+    let (rows, cols) = grid.size();
+    let cells = (0..rows).flat_map(|y| (0..cols).map(move |x| (x, y)));
+    let holds = |pos: (usize, usize), tile: &str| grid.get(pos).as_deref() == Some(tile);
+
+    match &rule.check {
+        LintCheck::NeverTouch { tile, other } => cells
+            .filter(|pos| holds(*pos, tile))
+            .flat_map(|pos| grid.neighbors(pos, connectivity).into_iter().map(move |next| (pos, next)))
+            .filter(|(_, next)| holds(*next, other))
+            // lava touching lava would come up from both sides
+            .filter(|(pos, next)| tile != other || pos_to_index(*pos, grid.size()) < pos_to_index(*next, grid.size()))
+            .map(|(pos, next)| grid_violation(rule, Some(pos), format!("{tile} touches {other} at ({},{})", next.0, next.1)))
+            .collect(),
+
+        LintCheck::Border { tile } => cells
+            .filter(|pos| pos.0 == 0 || pos.1 == 0 || pos.0 + 1 == cols || pos.1 + 1 == rows)
+            .filter(|pos| !holds(*pos, tile))
+            .map(|pos| grid_violation(rule, Some(pos), format!("edge should be {tile}")))
+            .collect(),
+
+        LintCheck::Count { tile, min, max } => {
+            let found: Vec<_> = cells.filter(|pos| holds(*pos, tile)).collect();
+            let count = found.len();
+
+            if min.is_some_and(|min| count < min) {
+                vec![grid_violation(rule, None, format!("{count} {tile}, needs at least {}", min.unwrap()))]
+            } else if max.is_some_and(|max| count > max) {
+                // every one of them, since any could be the extra
+                found.into_iter()
+                    .map(|pos| grid_violation(rule, Some(pos), format!("{count} {tile}, at most {} allowed", max.unwrap())))
+                    .collect()
+            } else {
+                vec![]
+            }
+        }
+
+        LintCheck::NoEmpty => cells
+            .filter(|pos| grid.get(*pos).is_none())
+            .map(|pos| grid_violation(rule, Some(pos), "empty cell".to_owned()))
+            .collect(),
+    }
+}
+
+// layer is the name of the grid's layer in a map, None for a plain grid
+pub fn lint_grid(grid: &TileGrid<String>, rules: &[LintRule], layer: Option<&str>, connectivity: Connectivity) -> Vec<LintViolation> {
+    rules.iter()
+        .filter(|rule| match (rule.layer.as_deref(), layer) {
+            (None, None) => true,
+            (None, Some(layer)) => layer == DEFAULT_LAYER_NAMES[0],
+            (Some(wanted), layer) => layer == Some(wanted),
+        })
+        .flat_map(|rule| check_grid(grid, rule, connectivity))
+        .map(|violation| LintViolation { layer: layer.map(str::to_owned), ..violation })
+        .collect()
+}
+
+pub fn lint_floors(floors: &FloorStack<String>, rules: &[LintRule]) -> Vec<LintViolation> {
+    let mut violations = vec![];
+
+    for (index, floor) in floors.floors().iter().enumerate() {
+        let connectivity = floor.topology.connectivity(Connectivity::Four);
+        for layer in floor.layers() {
+            violations.extend(
+                lint_grid(&layer.grid, rules, Some(&layer.name), connectivity)
+                    .into_iter()
+                    .map(|violation| LintViolation { floor: Some(index), ..violation })
+            );
+        }
+    }
+    return violations;
+}
+
+impl LintViolation {
+    // floor 2, items (3,4): one spawn: 2 spawn.png, at most 1 allowed
+    pub fn line(&self) -> String {
+        let mut place = vec![];
+        if let Some(floor) = self.floor { place.push(format!("floor {}", floor + 1)); }
+        if let Some(layer) = self.layer.as_ref() { place.push(layer.clone()); }
+        if let Some(pos) = self.pos { place.push(format!("({},{})", pos.0, pos.1)); }
+
+        if place.is_empty() { return format!("{}: {}", self.rule, self.message); }
+        return format!("{}: {}: {}", place.join(" "), self.rule, self.message);
+    }
+
+    // nulls for whatever it doesn't have, so every entry has the same keys
+    pub fn to_json(&self) -> JsonValue {
+        object! {
+            rule: self.rule.clone(),
+            floor: self.floor,
+            layer: self.layer.clone(),
+            x: self.pos.map(|pos| pos.0),
+            y: self.pos.map(|pos| pos.1),
+            message: self.message.clone(),
+        }
+    }
+}
+
+// `tile_editor lint [--json] <rules> <map>...`, --json prints one json object instead of lines.
+// Returns the exit code, 0 if every map is clean, 1 if anything broke a rule and 2 if the rules
+// or a map couldn't be read. the maps that could be still get linted
+pub fn run_lint(rules_path: &str, paths: &[&str], as_json: bool) -> i32 {
+    let rules = match load_lint_rules(Path::new(rules_path)) {
+        Ok(Some(rules)) => rules,
+        Ok(None) => {
+            eprintln!("{rules_path}: no rules file there");
+            return 2;
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            return 2;
+        }
+    };
+
+    let mut total = 0;
+    let mut unreadable = false;
+    let mut json_object = object! { violations: [] };
+
+    for path in paths {
+        let source = read_json_file(path).filter(|source| !source.is_null());
+        let Some(floors) = source.and_then(|source| FloorStack::<String>::from_json(&source)) else {
+            eprintln!("{path}: missing or not a valid map");
+            unreadable = true;
+            continue;
+        };
+        let violations = lint_floors(&floors, &rules);
+        total += violations.len();
+
+        for violation in violations {
+            if as_json {
+                let mut violation_json = violation.to_json();
+                violation_json["map"] = (*path).into();
+                json_object["violations"].push(violation_json).expect("violations is an array");
+            } else {
+                println!("{path}: {}", violation.line());
+            }
+        }
+    }
+
+    if as_json {
+        json_object["count"] = total.into();
+        println!("{}", json_object.pretty(4));
+    } else {
+        println!("{total} violations");
+    }
+    return if unreadable { 2 } else if total == 0 { 0 } else { 1 };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(check: LintCheck) -> LintRule {
+        LintRule { name: "rule".to_owned(), layer: None, check }
+    }
+
+    fn lint(rows: &[&str], check: LintCheck) -> Vec<LintViolation> {
+        lint_grid(&TileGrid::from_rows(rows), &[rule(check)], None, Connectivity::Four)
+    }
+
+    fn positions(violations: &[LintViolation]) -> Vec<Option<(usize, usize)>> {
+        violations.iter().map(|violation| violation.pos).collect()
+    }
+
+    #[test]
+    fn never_touch_reports_each_pair_once() {
+        let check = LintCheck::NeverTouch { tile: "l".to_owned(), other: "g".to_owned() };
+        assert_eq!(positions(&lint(&["lg", "g "], check)), vec![Some((0, 0)), Some((0, 0))]);
+
+        // the same tile on both ends would come up from both sides
+        let check = LintCheck::NeverTouch { tile: "l".to_owned(), other: "l".to_owned() };
+        assert_eq!(positions(&lint(&["ll", "  "], check.clone())), vec![Some((0, 0))]);
+        assert!(lint(&["l ", " l"], check).is_empty());
+    }
+
+    #[test]
+    fn border_wants_every_edge_cell() {
+        let check = LintCheck::Border { tile: "#".to_owned() };
+        assert!(lint(&["###", "# #", "###"], check.clone()).is_empty());
+        assert_eq!(positions(&lint(&["###", "#  ", "#.#"], check)), vec![Some((2, 1)), Some((1, 2))]);
+    }
+
+    #[test]
+    fn count_checks_both_ends() {
+        let check = |min, max| LintCheck::Count { tile: "s".to_owned(), min, max };
+
+        assert_eq!(positions(&lint(&["   "], check(Some(1), None))), vec![None]);
+        assert!(lint(&["s  "], check(Some(1), Some(1))).is_empty());
+        // too many lists every one, any of them could be the extra
+        assert_eq!(positions(&lint(&["s s"], check(None, Some(1)))), vec![Some((0, 0)), Some((2, 0))]);
+        assert!(lint(&["sss"], check(None, None)).is_empty());
+    }
+
+    #[test]
+    fn no_empty_finds_the_holes() {
+        assert_eq!(positions(&lint(&["a b", "ab "], LintCheck::NoEmpty)), vec![Some((1, 0)), Some((2, 1))]);
+    }
+
+    #[test]
+    fn rules_only_look_at_their_layer() {
+        let grid = TileGrid::from_rows(&[" "]);
+        let ground = rule(LintCheck::NoEmpty);
+        let items = LintRule { layer: Some("items".to_owned()), ..rule(LintCheck::NoEmpty) };
+        let rules = [ground, items];
+
+        let layers_hit = |layer| -> Vec<Option<String>> {
+            lint_grid(&grid, &rules, layer, Connectivity::Four).into_iter().map(|violation| violation.layer).collect()
+        };
+
+        // rules without a layer are for the ground layer, or a plain grid
+        assert_eq!(layers_hit(Some(DEFAULT_LAYER_NAMES[0])), vec![Some(DEFAULT_LAYER_NAMES[0].to_owned())]);
+        assert_eq!(layers_hit(Some("items")), vec![Some("items".to_owned())]);
+        assert_eq!(layers_hit(Some("decals")), vec![]);
+        assert_eq!(layers_hit(None), vec![None]);
+    }
+
+    #[test]
+    fn rules_round_trip_through_json() {
+        let rules = [
+            LintRule { name: "no lava by grass".to_owned(), layer: None, check: LintCheck::NeverTouch { tile: "lava".to_owned(), other: "grass".to_owned() } },
+            LintRule { name: "walled in".to_owned(), layer: Some("walls".to_owned()), check: LintCheck::Border { tile: "wall".to_owned() } },
+            LintRule { name: "one spawn".to_owned(), layer: None, check: LintCheck::Count { tile: "spawn".to_owned(), min: Some(1), max: None } },
+            LintRule { name: "no holes".to_owned(), layer: None, check: LintCheck::NoEmpty },
+        ];
+        for rule in rules {
+            assert_eq!(LintRule::from_json(&json::parse(&rule.to_json().dump()).unwrap()), Some(rule));
+        }
+
+        // the kind does for a name, an unknown kind isn't a rule
+        let unnamed = json::parse(r#"{ "kind": "no_empty" }"#).unwrap();
+        assert_eq!(LintRule::from_json(&unnamed).map(|rule| rule.name), Some("no_empty".to_owned()));
+        assert_eq!(LintRule::from_json(&json::parse(r#"{ "kind": "no_lava" }"#).unwrap()), None);
+    }
+
+    #[test]
+    fn broken_rules_and_maps_exit_2() {
+        let dir = std::env::temp_dir().join(format!("tile_editor_lint_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_owned();
+
+        fs::write(path("rules.json"), r#"{ "version": "1.0", "rules": [{ "kind": "no_empty" }] }"#).unwrap();
+        fs::write(path("bad rules.json"), r#"{ "version": "1.0", "rules": [{ "kind": "no_lava" }] }"#).unwrap();
+        fs::write(path("full.json"), TileGrid::from_rows(&["ab"]).to_json().dump()).unwrap();
+        fs::write(path("holes.json"), TileGrid::from_rows(&["a "]).to_json().dump()).unwrap();
+        fs::write(path("not json.json"), "{ oops").unwrap();
+
+        let bad_rules = load_lint_rules(Path::new(&path("bad rules.json")));
+        let results = [
+            run_lint(&path("rules.json"), &[&path("full.json")], false),
+            run_lint(&path("rules.json"), &[&path("holes.json")], false),
+            run_lint(&path("rules.json"), &[&path("not json.json"), &path("full.json")], false),
+            run_lint(&path("missing.json"), &[&path("full.json")], false),
+            run_lint(&path("bad rules.json"), &[&path("full.json")], false),
+        ];
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(bad_rules.is_err());
+        assert_eq!(results, [0, 1, 2, 2, 2]);
+    }
+}
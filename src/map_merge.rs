@@ -0,0 +1,527 @@
+// diff and three way merge for map files, so git can deal with them.
+// works on plain grids, single floor maps and whole floor stacks
+
+use std::fs;
+use std::io::{Read, Write};
+
+use json::JsonValue;
+
+use crate::tile_grid::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridDiff {
+    pub old_size: (usize, usize),
+    pub new_size: (usize, usize),
+    // every cell that's different, item, note or orientation. in the new grid's cells,
+    // so a cell that got cut off the left or top is negative.
+    // cells that only one side has count if they aren't empty
+    pub changed: Vec<(i64, i64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GridMerge<T> {
+    pub grid: TileGrid<T>,
+    // both sides resized differently, ours wins
+    pub size_conflict: bool,
+    // both sides changed these cells differently, ours wins
+    pub conflicts: Vec<(usize, usize)>,
+    // changed on one side but outside the merged size, so the change is gone.
+    // in the merged grid's cells, negative is off the left or top
+    pub cut_off: Vec<(i64, i64)>,
+}
+
+// grids line up by their origin, so a resize from the left or top doesn't shift every cell.
+// pos is in the cells of a grid with its origin at from
+fn pos_in<T>(grid: &TileGrid<T>, from: (i64, i64), pos: (i64, i64)) -> Option<(usize, usize)> {
+    let x = pos.0 + from.0 - grid.origin.0;
+    let y = pos.1 + from.1 - grid.origin.1;
+    if x < 0 || y < 0 || x >= grid.cols as i64 || y >= grid.rows as i64 { return None; }
+    return Some((x as usize, y as usize));
+}
+
+// off the edge is the same as an empty cell
+fn tile_or_empty<T: PartialEq>(grid: &TileGrid<T>, from: (i64, i64), pos: (i64, i64)) -> Option<&Tile<T>> {
+    let tile = grid.get_tile(pos_in(grid, from, pos)?);
+    if *tile == Tile::default() { return None; }
+    return Some(tile);
+}
+
+// Returns the min and max (exclusive) corners of every grid put together, in cells of a grid at from
+fn bounds_of<T>(grids: &[&TileGrid<T>], from: (i64, i64)) -> ((i64, i64), (i64, i64)) {
+    let min_x = grids.iter().map(|grid| grid.origin.0 - from.0).min().unwrap_or(0);
+    let min_y = grids.iter().map(|grid| grid.origin.1 - from.1).min().unwrap_or(0);
+    let max_x = grids.iter().map(|grid| grid.origin.0 - from.0 + grid.cols as i64).max().unwrap_or(0);
+    let max_y = grids.iter().map(|grid| grid.origin.1 - from.1 + grid.rows as i64).max().unwrap_or(0);
+    ((min_x, min_y), (max_x, max_y))
+}
+
+pub fn diff_grids<T: PartialEq>(old: &TileGrid<T>, new: &TileGrid<T>) -> GridDiff {
+    let ((min_x, min_y), (max_x, max_y)) = bounds_of(&[old, new], new.origin);
+
+    let mut changed = vec![];
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            if tile_or_empty(old, new.origin, (x, y)) != tile_or_empty(new, new.origin, (x, y)) {
+                changed.push((x, y));
+            }
+        }
+    }
+
+    GridDiff { old_size: old.size(), new_size: new.size(), changed }
+}
+
+// cell by cell. a side that didn't touch a cell takes the other side's change
+pub fn merge_grids<T: Clone + PartialEq>(base: &TileGrid<T>, ours: &TileGrid<T>, theirs: &TileGrid<T>) -> GridMerge<T> {
+    // moving the origin without changing the size still shifts every cell, so that's a resize too
+    let same_shape = |a: &TileGrid<T>, b: &TileGrid<T>| a.size() == b.size() && a.origin == b.origin;
+    let size_conflict = !same_shape(ours, theirs) && !same_shape(ours, base) && !same_shape(theirs, base);
+    let shape = if same_shape(ours, base) { theirs } else { ours };
+
+    let mut grid = TileGrid::new(shape.rows, shape.cols);
+    grid.origin = shape.origin;
+    let mut conflicts = vec![];
+    let mut cut_off = vec![];
+
+    // all the way out around all three, so edits a shrink cut off still get seen
+    let ((min_x, min_y), (max_x, max_y)) = bounds_of(&[base, ours, theirs], grid.origin);
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let pos = (x, y);
+            let (b, o, t) = (tile_or_empty(base, grid.origin, pos), tile_or_empty(ours, grid.origin, pos), tile_or_empty(theirs, grid.origin, pos));
+
+            let Some(grid_pos) = pos_in(&grid, grid.origin, pos) else {
+                // the side that shrank has nothing here, so anything left is the other side's edit
+                if (o.is_some() && o != b) || (t.is_some() && t != b) { cut_off.push(pos); }
+                continue;
+            };
+
+            let tile = if o == t || t == b {
+                o
+            } else if o == b {
+                t
+            } else {
+                conflicts.push(grid_pos);
+                o
+            };
+
+            if let Some(tile) = tile { grid.set_tile(grid_pos, tile.clone()); }
+        }
+    }
+
+    GridMerge { grid, size_conflict, conflicts, cut_off }
+}
+
+// "wall.png", "empty", with the note and orientation if it has them.
+// pos is in the cells of a grid with its origin at from
+fn describe_cell(grid: &TileGrid<String>, from: (i64, i64), pos: (i64, i64)) -> String {
+    if tile_or_empty(grid, from, pos).is_none() { return "empty".to_owned(); }
+    let pos = pos_in(grid, from, pos).expect("Not empty so it's on the grid");
+
+    let mut text = grid.get(pos).clone().unwrap_or("empty".to_owned());
+    let orientation = grid.get_orientation(pos);
+    if !orientation.is_default() {
+        text += &format!(" turned {}", orientation.quarter_turns as u32 * 90);
+        if orientation.flip_x { text += " flip x"; }
+        if orientation.flip_y { text += " flip y"; }
+    }
+    if let Some(note) = grid.get_note(pos) {
+        text += &format!(" (note: {note})");
+    }
+    return text;
+}
+
+fn empty_grid_like(grid: &TileGrid<String>) -> TileGrid<String> {
+    TileGrid::new(grid.rows, grid.cols)
+}
+
+fn parse_grid(source: &JsonValue) -> Option<TileGrid<String>> {
+    TileGrid::from_json(source)
+}
+
+// every grid in a map file, with a name for it. plain grids, single floor maps
+// and floor stacks are all map files. Returns None if any of them is broken
+pub fn grids_in_map(source: &JsonValue) -> Option<Vec<(String, TileGrid<String>)>> {
+    let layers_of = |map: &JsonValue, prefix: &str| -> Option<Vec<(String, TileGrid<String>)>> {
+        if map["layers"].is_null() {
+            return Some(vec![(format!("{prefix}grid"), parse_grid(map)?)]);
+        }
+        map["layers"]
+            .members()
+            .map(|layer| Some((format!("{prefix}layer {}", layer["name"].as_str().unwrap_or("?")), parse_grid(layer)?)))
+            .collect()
+    };
+
+    if source["floors"].is_null() {
+        return layers_of(source, "");
+    }
+
+    source["floors"]
+        .members()
+        .enumerate()
+        .map(|(i, floor)| layers_of(floor, &format!("floor {i} ")))
+        .collect::<Option<Vec<_>>>()
+        .map(|floors| floors.concat())
+}
+
+// Returns a line for every difference, nothing if they're the same. None if either has a broken grid
+pub fn diff_maps(old: &JsonValue, new: &JsonValue) -> Option<Vec<String>> {
+    let old_grids = grids_in_map(old)?;
+    let new_grids = grids_in_map(new)?;
+
+    let mut lines = vec![];
+    for (name, old_grid) in old_grids.iter() {
+        let Some((_, new_grid)) = new_grids.iter().find(|(new_name, _)| new_name == name) else {
+            lines.push(format!("{name}: removed"));
+            continue;
+        };
+
+        let diff = diff_grids(old_grid, new_grid);
+        if diff.old_size != diff.new_size {
+            lines.push(format!(
+                "{name}: size {}x{} -> {}x{} (cols x rows)",
+                diff.old_size.1, diff.old_size.0, diff.new_size.1, diff.new_size.0
+            ));
+        }
+        for pos in diff.changed {
+            lines.push(format!(
+                "{name} ({},{}): {} -> {}",
+                pos.0, pos.1, describe_cell(old_grid, new_grid.origin, pos), describe_cell(new_grid, new_grid.origin, pos)
+            ));
+        }
+    }
+    for (name, _) in new_grids.iter() {
+        if !old_grids.iter().any(|(old_name, _)| old_name == name) {
+            lines.push(format!("{name}: added"));
+        }
+    }
+
+    // links, the palette, ect. just get a yes or no
+    for key in ["links", "palette"] {
+        if old[key] != new[key] {
+            lines.push(format!("{key}: changed"));
+        }
+    }
+
+    // regions and objects are on each floor
+    let floors_of = |source: &JsonValue| -> Vec<JsonValue> {
+        if source["floors"].is_null() { vec![source.clone()] } else { source["floors"].members().cloned().collect() }
+    };
+    let (old_floors, new_floors) = (floors_of(old), floors_of(new));
+    for (i, (old_floor, new_floor)) in old_floors.iter().zip(new_floors.iter()).enumerate() {
+        let prefix = if old["floors"].is_null() { String::new() } else { format!("floor {i} ") };
+        for key in ["regions", "objects"] {
+            if old_floor[key] != new_floor[key] {
+                lines.push(format!("{prefix}{key}: changed"));
+            }
+        }
+    }
+
+    return Some(lines);
+}
+
+// anything that isn't a grid gets merged whole, like links or the active layer
+fn merge_value(name: &str, base: &JsonValue, ours: &JsonValue, theirs: &JsonValue, conflicts: &mut Vec<String>) -> JsonValue {
+    if ours == theirs || theirs == base { return ours.clone(); }
+    if ours == base { return theirs.clone(); }
+
+    conflicts.push(format!("{name}: changed on both sides, kept ours"));
+    return ours.clone();
+}
+
+// merges every key but the ones in skip
+fn merge_other_keys(prefix: &str, skip: &[&str], base: &JsonValue, ours: &JsonValue, theirs: &JsonValue, result: &mut JsonValue, conflicts: &mut Vec<String>) {
+    let mut keys: Vec<&str> = ours.entries().chain(theirs.entries()).map(|(key, _)| key).collect();
+    keys.sort();
+    keys.dedup();
+
+    for key in keys {
+        if skip.contains(&key) { continue; }
+        let merged = merge_value(&format!("{prefix}{key}"), &base[key], &ours[key], &theirs[key], conflicts);
+        if merged.is_null() { result.remove(key); } else { result[key] = merged; }
+    }
+}
+
+fn merge_grid_json(name: &str, base: &JsonValue, ours: &JsonValue, theirs: &JsonValue, conflicts: &mut Vec<String>) -> Option<JsonValue> {
+    let (ours_grid, theirs_grid) = (parse_grid(ours)?, parse_grid(theirs)?);
+    // made on both sides at once, nothing in common
+    let base_grid = if base.is_null() { empty_grid_like(&ours_grid) } else { parse_grid(base)? };
+
+    let merge = merge_grids(&base_grid, &ours_grid, &theirs_grid);
+    if merge.size_conflict {
+        conflicts.push(format!(
+            "{name}: resized on both sides, ours is {}x{}, theirs is {}x{} (cols x rows), kept ours",
+            ours_grid.cols, ours_grid.rows, theirs_grid.cols, theirs_grid.rows
+        ));
+    }
+    let origin = merge.grid.origin;
+    for pos in merge.conflicts.iter() {
+        let pos = (pos.0 as i64, pos.1 as i64);
+        conflicts.push(format!(
+            "{name} ({},{}): ours has {}, theirs has {}, kept ours",
+            pos.0, pos.1, describe_cell(&ours_grid, origin, pos), describe_cell(&theirs_grid, origin, pos)
+        ));
+    }
+    for pos in merge.cut_off.iter() {
+        conflicts.push(format!(
+            "{name} ({},{}): outside the merged size, ours has {}, theirs has {}, left out",
+            pos.0, pos.1, describe_cell(&ours_grid, origin, *pos), describe_cell(&theirs_grid, origin, *pos)
+        ));
+    }
+
+    return Some(merge.grid.to_json());
+}
+
+fn merge_map_json(prefix: &str, base: &JsonValue, ours: &JsonValue, theirs: &JsonValue, conflicts: &mut Vec<String>) -> Option<JsonValue> {
+    if ours["layers"].is_null() || theirs["layers"].is_null() {
+        return merge_grid_json(&format!("{prefix}grid"), base, ours, theirs, conflicts);
+    }
+
+    let mut result = ours.clone();
+    merge_other_keys(prefix, &["layers", "rows", "cols"], base, ours, theirs, &mut result, conflicts);
+
+    // layers line up by name
+    let find_layer = |map: &JsonValue, name: &str| -> JsonValue {
+        map["layers"].members().find(|layer| layer["name"] == name).cloned().unwrap_or(JsonValue::Null)
+    };
+
+    for (i, ours_layer) in ours["layers"].members().enumerate() {
+        let layer_name = ours_layer["name"].as_str().unwrap_or("?");
+        let name = format!("{prefix}layer {layer_name}");
+
+        let theirs_layer = find_layer(theirs, layer_name);
+        if theirs_layer.is_null() {
+            conflicts.push(format!("{name}: only ours has it, kept ours"));
+            continue;
+        }
+
+        let mut merged = merge_grid_json(&name, &find_layer(base, layer_name), ours_layer, &theirs_layer, conflicts)?;
+        merged["name"] = layer_name.into();
+        result["layers"][i] = merged;
+    }
+    for theirs_layer in theirs["layers"].members() {
+        if find_layer(ours, theirs_layer["name"].as_str().unwrap_or("?")).is_null() {
+            conflicts.push(format!("{prefix}layer {}: only theirs has it, left out", theirs_layer["name"]));
+        }
+    }
+
+    // the layers all resize together, so they all agree on this
+    result["rows"] = result["layers"][0]["rows"].clone();
+    result["cols"] = result["layers"][0]["cols"].clone();
+
+    return Some(result);
+}
+
+// Returns the merged map, and a line for every conflict. conflicts keep ours.
+// None if any of the three has a broken grid
+pub fn merge_maps(base: &JsonValue, ours: &JsonValue, theirs: &JsonValue) -> Option<(JsonValue, Vec<String>)> {
+    let mut conflicts = vec![];
+
+    if ours["floors"].is_null() && theirs["floors"].is_null() {
+        let merged = merge_map_json("", base, ours, theirs, &mut conflicts)?;
+        return Some((merged, conflicts));
+    }
+    if ours["floors"].is_null() || theirs["floors"].is_null() {
+        conflicts.push("one side is a floor stack and the other isn't, kept ours".to_owned());
+        return Some((ours.clone(), conflicts));
+    }
+
+    let mut result = ours.clone();
+    merge_other_keys("", &["floors"], base, ours, theirs, &mut result, &mut conflicts);
+
+    if ours["floors"].len() != theirs["floors"].len() {
+        conflicts.push(format!(
+            "floors: ours has {}, theirs has {}, kept ours",
+            ours["floors"].len(), theirs["floors"].len()
+        ));
+        result["floors"] = ours["floors"].clone();
+        return Some((result, conflicts));
+    }
+
+    for i in 0..ours["floors"].len() {
+        let prefix = format!("floor {i} ");
+        result["floors"][i] = merge_map_json(&prefix, &base["floors"][i], &ours["floors"][i], &theirs["floors"][i], &mut conflicts)?;
+    }
+
+    return Some((result, conflicts));
+}
+
+// a missing or empty file is null, git hands the merge driver an empty base when both sides added the file.
+// Returns None if it can't be read or isn't json, like a file with conflict markers in it
+pub fn read_json_file(path: &str) -> Option<JsonValue> {
+    let mut buffer = String::new();
+    let Ok(mut input) = fs::File::open(path) else { return Some(JsonValue::Null); };
+    input.read_to_string(&mut buffer).ok()?;
+    if buffer.trim().is_empty() { return Some(JsonValue::Null); }
+    json::parse(&buffer).ok()
+}
+
+// a plain grid, a single floor map or a floor stack. palettes, terrain sets, ect. aren't
+pub fn is_map_json(source: &JsonValue) -> bool {
+    !source["floors"].is_null() || !source["layers"].is_null() || (source["rows"].is_number() && source["cols"].is_number())
+}
+
+// `tile_editor diff <old> <new>`
+// Returns the exit code, 0 if they're the same and 1 if not
+pub fn run_diff(old_path: &str, new_path: &str) -> i32 {
+    let (Some(old), Some(new)) = (read_json_file(old_path), read_json_file(new_path)) else {
+        eprintln!("can't diff, both files have to be valid json");
+        return 2;
+    };
+    if !is_map_json(&old) || !is_map_json(&new) {
+        eprintln!("can't diff, both files have to be maps");
+        return 2;
+    }
+
+    let Some(lines) = diff_maps(&old, &new) else {
+        eprintln!("can't diff, one of the grids is broken");
+        return 2;
+    };
+    for line in lines.iter() {
+        println!("{line}");
+    }
+    return if lines.is_empty() { 0 } else { 1 };
+}
+
+// `tile_editor merge <base> <ours> <theirs>`, the result is written over ours.
+// git merge driver: `driver = tile_editor merge %O %A %B`
+// Returns the exit code, 0 if it merged clean, 1 if there were conflicts and 2 if they aren't
+// good map files. ours is left alone when they aren't, so git falls back to a conflict
+pub fn run_merge(base_path: &str, ours_path: &str, theirs_path: &str) -> i32 {
+    let (Some(base), Some(ours), Some(theirs)) = (read_json_file(base_path), read_json_file(ours_path), read_json_file(theirs_path)) else {
+        eprintln!("can't merge, one of the files isn't valid json");
+        return 2;
+    };
+    if !is_map_json(&ours) || !is_map_json(&theirs) || !(base.is_null() || is_map_json(&base)) {
+        eprintln!("can't merge, these aren't map files");
+        return 2;
+    }
+
+    let Some((merged, conflicts)) = merge_maps(&base, &ours, &theirs) else {
+        eprintln!("can't merge, one of the grids is broken");
+        return 2;
+    };
+
+    let written = fs::File::create(ours_path).and_then(|mut output| write!(output, "{}", merged));
+    if written.is_err() {
+        eprintln!("can't write the merge to {ours_path}");
+        return 2;
+    }
+
+    for conflict in conflicts.iter() {
+        println!("CONFLICT {conflict}");
+    }
+    return if conflicts.is_empty() { 0 } else { 1 };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_edits_from_both_sides() {
+        let merge = merge_grids(&TileGrid::from_rows(&["aa", "aa"]), &TileGrid::from_rows(&["ba", "aa"]), &TileGrid::from_rows(&["aa", "ac"]));
+        assert_eq!(merge.grid.get((0, 0)).as_deref(), Some("b"));
+        assert_eq!(merge.grid.get((1, 1)).as_deref(), Some("c"));
+        assert!(merge.conflicts.is_empty() && merge.cut_off.is_empty());
+    }
+
+    #[test]
+    fn same_cell_changed_both_ways_is_a_conflict() {
+        let merge = merge_grids(&TileGrid::from_rows(&["a"]), &TileGrid::from_rows(&["b"]), &TileGrid::from_rows(&["c"]));
+        assert_eq!(merge.grid.get((0, 0)).as_deref(), Some("b"));
+        assert_eq!(merge.conflicts, vec![(0, 0)]);
+    }
+
+    #[test]
+    fn edits_in_a_shrunk_area_are_reported() {
+        // ours dropped the last column, theirs painted in it
+        let merge = merge_grids(&TileGrid::from_rows(&["aaa"]), &TileGrid::from_rows(&["aa"]), &TileGrid::from_rows(&["aab"]));
+        assert_eq!(merge.grid.size(), (1, 2));
+        assert_eq!(merge.cut_off, vec![(2, 0)]);
+
+        // nobody touched it, the shrink is all that happened
+        let merge = merge_grids(&TileGrid::from_rows(&["aaa"]), &TileGrid::from_rows(&["aa"]), &TileGrid::from_rows(&["aaa"]));
+        assert!(merge.cut_off.is_empty());
+    }
+
+    #[test]
+    fn edits_follow_cells_a_left_resize_moved() {
+        let base = TileGrid::from_rows(&["abc"]);
+        // ours grew a column on the left, theirs changed the c
+        let mut ours = base.clone();
+        ours.resize_edges(EdgeDeltas::anchored(ours.size(), (1, 4), Anchor::TopRight));
+        let theirs = TileGrid::from_rows(&["abx"]);
+
+        let merge = merge_grids(&base, &ours, &theirs);
+        assert!(merge.conflicts.is_empty() && merge.cut_off.is_empty() && !merge.size_conflict);
+        assert_eq!(merge.grid.origin, (-1, 0));
+        assert_eq!(merge.grid.get((3, 0)).as_deref(), Some("x"));
+        assert_eq!(merge.grid.get((2, 0)).as_deref(), Some("b"));
+
+        // the other way around lands in the same place
+        let merge = merge_grids(&base, &theirs, &ours);
+        assert_eq!(merge.grid.get((3, 0)).as_deref(), Some("x"));
+
+        // and it survives the trip through the file
+        let (merged, conflicts) = merge_maps(&base.to_json(), &ours.to_json(), &theirs.to_json()).unwrap();
+        assert!(conflicts.is_empty());
+        let merged = parse_grid(&merged).unwrap();
+        assert_eq!(merged.origin, (-1, 0));
+        assert_eq!(merged.get((3, 0)).as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn edits_cut_off_the_left_are_reported() {
+        let base = TileGrid::from_rows(&["abc"]);
+        let mut ours = base.clone();
+        ours.resize_edges(EdgeDeltas::anchored(ours.size(), (1, 2), Anchor::TopRight));
+        let theirs = TileGrid::from_rows(&["xbc"]);
+
+        let merge = merge_grids(&base, &ours, &theirs);
+        assert_eq!(merge.cut_off, vec![(-1, 0)]);
+        assert_eq!(merge.grid.get((0, 0)).as_deref(), Some("b"));
+
+        let diff = diff_grids(&base, &ours);
+        assert_eq!(diff.changed, vec![(-1, 0)]);
+    }
+
+    #[test]
+    fn empty_base_merges_as_added_on_both_sides() {
+        let (ours, theirs) = (TileGrid::from_rows(&["a "]).to_json(), TileGrid::from_rows(&[" b"]).to_json());
+        let (merged, conflicts) = merge_maps(&JsonValue::Null, &ours, &theirs).unwrap();
+        assert!(conflicts.is_empty());
+        assert_eq!(parse_grid(&merged).unwrap().get((1, 0)).as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn broken_files_exit_2_and_leave_ours_alone() {
+        let dir = std::env::temp_dir().join(format!("tile_editor_merge_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_owned();
+
+        let ours = TileGrid::from_rows(&["a"]).to_json().dump();
+        fs::write(path("base.json"), TileGrid::from_rows(&["b"]).to_json().dump()).unwrap();
+        fs::write(path("ours.json"), &ours).unwrap();
+        // conflict markers from an earlier merge, and a grid that only looks like one
+        fs::write(path("markers.json"), "<<<<<<< HEAD\n{}\n=======\n{}\n>>>>>>> theirs\n").unwrap();
+        fs::write(path("fake.json"), r#"{ "rows": 1, "cols": 1 }"#).unwrap();
+
+        for theirs in ["markers.json", "fake.json"] {
+            assert_eq!(run_merge(&path("base.json"), &path("ours.json"), &path(theirs)), 2);
+            assert_eq!(run_diff(&path("ours.json"), &path(theirs)), 2);
+        }
+        let after = fs::read_to_string(path("ours.json")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(after, ours);
+    }
+
+    #[test]
+    fn only_maps_count_as_maps() {
+        assert!(is_map_json(&TileGrid::from_rows(&["a"]).to_json()));
+        assert!(!is_map_json(&json::parse(r#"{ "version": "1.0", "rules": [] }"#).unwrap()));
+        assert!(!is_map_json(&JsonValue::Null));
+    }
+}
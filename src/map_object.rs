@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+use json::{object, JsonValue};
+
+use crate::tile_grid::*;
+use crate::tile_definition::TileProperty;
+
+// spawn points, npcs, lights, ect. things that sit on top of the map
+// without being stuck to a cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapObject {
+    pub name: String,
+    // an icon name, same as what goes in the grid
+    pub icon: String,
+    // in cells from the top left corner of the grid, so (0.5, 0.5) is the middle of the first cell
+    pub position: (f32, f32),
+    pub properties: BTreeMap<String, TileProperty>,
+}
+
+impl MapObject {
+    pub fn new(name: &str, icon: &str, position: (f32, f32)) -> Self {
+        MapObject { name: name.to_owned(), icon: icon.to_owned(), position, properties: BTreeMap::new() }
+    }
+}
+
+// going straight to f64 drags along noise, 0.1 turns into 0.10000000149011612.
+// the shortest text of the f32 reads back as the same f32 and looks like what was typed
+fn f32_to_json(value: f32) -> JsonValue {
+    JsonValue::from(value.to_string().parse::<f64>().expect("f32 prints as a valid f64"))
+}
+
+impl ToAndFromJsonValue for MapObject {
+    fn to_json(&self) -> JsonValue {
+        let mut json_object = object! {
+            name: self.name.clone(),
+            icon: self.icon.clone(),
+            x: f32_to_json(self.position.0),
+            y: f32_to_json(self.position.1),
+            properties: {},
+        };
+
+        for (name, property) in self.properties.iter() {
+            json_object["properties"][name.as_str()] = property.to_json();
+        }
+
+        return json_object;
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        let mut map_object = MapObject::new(
+            source["name"].as_str()?,
+            source["icon"].as_str()?,
+            (source["x"].as_f32()?, source["y"].as_f32()?),
+        );
+
+        for (name, property) in source["properties"].entries() {
+            map_object.properties.insert(name.to_owned(), TileProperty::from_json(property)?);
+        }
+
+        return Some(map_object);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_save_as_short_numbers() {
+        let map_object = MapObject::new("torch", "torch.png", (0.1, 3.25));
+        let json = map_object.to_json();
+        assert_eq!(json["x"].dump(), "0.1");
+        assert_eq!(MapObject::from_json(&json::parse(&json.dump()).unwrap()), Some(map_object));
+    }
+}
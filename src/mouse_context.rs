@@ -9,7 +9,9 @@ pub struct MouseContext {
     pub mouse_delta :  Vector2,
     pub mouse_left_pressed  : bool,
     pub mouse_left_released : bool,
+    pub mouse_left_down     : bool,
     pub mouse_right_pressed : bool,
+    pub mouse_right_down    : bool,
 
 	// TODO: Get key presses in here
 }
@@ -22,7 +24,9 @@ impl MouseContext {
             
 			mouse_left_pressed  : rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT),
             mouse_left_released : rl.is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT),
+            mouse_left_down     : rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT),
             mouse_right_pressed : rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT),
+            mouse_right_down    : rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_RIGHT),
         }
 	}
 
@@ -34,7 +38,9 @@ impl MouseContext {
 
             mouse_left_pressed  : false,
             mouse_left_released : false,
+            mouse_left_down     : false,
             mouse_right_pressed : false,
+            mouse_right_down    : false,
         }
 	}
 }
\ No newline at end of file
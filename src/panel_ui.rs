@@ -4,6 +4,8 @@ use std::cmp::min;
 use raylib::prelude::*;
 
 use crate::MouseContext;
+use crate::topology::*;
+use crate::projection::*;
 
 type Vector2 = raylib::math::Vector2;
 type Rectangle = raylib::math::Rectangle;
@@ -176,6 +178,43 @@ impl DrawableObject for &Texture2D {
 	}
 }
 
+// a texture that can be mirrored and turned in place.
+// flips happen first, then quarter_turns clockwise
+pub struct OrientedTexture<'a> {
+	pub texture: &'a Texture2D,
+	pub quarter_turns: u8,
+	pub flip_x: bool,
+	pub flip_y: bool,
+}
+
+impl DrawableObject for OrientedTexture<'_> {
+	fn draw(&self, d: &mut RaylibDrawHandle, rec: Rectangle) {
+		assert!(self.texture.width  == rec.width  as i32);
+		assert!(self.texture.height == rec.height as i32);
+
+		// negative source size is how raylib mirrors
+		let source = Rectangle {
+			x: 0.0, y: 0.0,
+			width:  if self.flip_x { -rec.width  } else { rec.width  },
+			height: if self.flip_y { -rec.height } else { rec.height },
+		};
+		// rotation goes around the origin, so put it in the middle
+		let half = Vector2 { x: rec.width / 2.0, y: rec.height / 2.0 };
+		let dest = Rectangle { x: rec.x + half.x, y: rec.y + half.y, ..rec };
+
+		d.draw_texture_pro(self.texture, source, dest, half, self.quarter_turns as f32 * 90.0, Color::WHITE)
+	}
+}
+
+// draws them in order, so the last one ends up on top
+impl<T : DrawableObject> DrawableObject for Vec<T> {
+	fn draw(&self, d: &mut RaylibDrawHandle, rec: Rectangle) {
+		for drawable in self.iter() {
+			drawable.draw(d, rec);
+		}
+	}
+}
+
 // TODO: make this accept a drawable object?
 // the only two objects i have in mind are text and images
 // i could even just impl those cases in this file
@@ -376,6 +415,61 @@ impl PanelLike for TextPanel {
 }
 
 
+// one texture stretched to a square, for little things you pick up and move around.
+// no texture just draws the highlight, so you can still find it
+pub struct ImagePanel<'a> {
+	drag_context: PanelUiDragContext,
+
+	pub texture: Option<&'a Texture2D>,
+	pub size: f32,
+
+	pub highlight_color: Option<Color>,
+}
+
+impl<'a> ImagePanel<'a> {
+	pub fn new_custom(drag_context: PanelUiDragContext, texture: Option<&'a Texture2D>, size: f32, highlight_color: Option<Color>) -> Self {
+		Self { drag_context, texture, size, highlight_color }
+	}
+}
+
+impl PanelLike for ImagePanel<'_> {
+	fn new() -> Self {
+		Self {
+			drag_context: PanelUiDragContext::default(),
+			texture: None,
+			size: 32.0,
+			highlight_color: None,
+		}
+	}
+
+	fn width(&self)  -> f32 { self.size }
+	fn height(&self) -> f32 { self.size }
+
+	// its a square, so these both do the same thing
+	fn set_width (&mut self, width : f32) { self.size = width  }
+	fn set_height(&mut self, height: f32) { self.size = height }
+
+	fn get_drag_context(&self) -> PanelUiDragContext                 { self.drag_context }
+	fn set_drag_context(&mut self, drag_context: PanelUiDragContext) { self.drag_context = drag_context; }
+
+	// theres only the one thing in here
+	fn get_hovered_id_at(&self, mouse_context: &MouseContext, position: Vector2) -> Option<usize> {
+		if self.mouse_over_panel_at(mouse_context, position) { Some(0) } else { None }
+	}
+
+	fn draw_panel_at(&self, d: &mut RaylibDrawHandle, _mouse_context: &MouseContext, position: Vector2) {
+		let rec = self.as_rec_at(position);
+
+		if let Some(highlight_color) = self.highlight_color {
+			d.draw_rectangle_lines_ex(pad_rectangle(rec, 2.0), 2.0, highlight_color);
+		}
+
+		let Some(texture) = self.texture else { return; };
+		let source = Rectangle { x: 0.0, y: 0.0, width: texture.width as f32, height: texture.height as f32 };
+		d.draw_texture_pro(texture, source, rec, Vector2::zero(), 0.0, Color::WHITE);
+	}
+}
+
 // check weather a point is in a rectangle
 pub fn point_rec_collision(point: Vector2, rec: Rectangle) -> bool {
 	return (rec.x <= point.x && point.x <= rec.x + rec.width )
@@ -562,8 +656,16 @@ pub struct GridPanel<T : DrawableObject> {
 
 	pub highlight_color  : Option<Color>,
 	pub background_color : Option<Color>,
+
+	// hex grids push every other row/col over, and hit test against the hexes
+	pub topology : Topology,
+	// only for square grids, hex ones are always top down
+	pub projection : Projection,
 }
 
+const HEX_OUTLINE_COLOR: Color = Color::GRAY;
+const ISO_OUTLINE_COLOR: Color = Color::GRAY;
+
 impl<T : DrawableObject> GridPanel<T> {
 	pub fn new_custom(
 		position: Vector2,
@@ -605,8 +707,46 @@ impl<T : DrawableObject> GridPanel<T> {
 		length * num_wide + self.item_padding * (num_wide - 1)
 	}
 
+	// (x, y) of the item, x being along the run when its by cols
+	fn cell_of_item(&self, index: usize) -> (usize, usize) {
+		if self.by_cols {
+			(index % self.run_length, index / self.run_length)
+		} else {
+			(index / self.run_length, index % self.run_length)
+		}
+	}
+
+	// the distance between the middles of two neighbouring hexes
+	fn hex_spacing(&self) -> f32 {
+		(self.item_width + self.item_padding) as f32
+	}
+
+	// None unless it's a square grid drawn isometric
+	pub fn iso_layout(&self) -> Option<IsoLayout> {
+		if self.topology != Topology::Square || self.projection != Projection::Isometric { return None; }
+
+		let cell_width = (self.item_width + self.item_padding) as f32;
+		Some(IsoLayout {
+			size: self.grid_dimensions(),
+			cell_width,
+			overhang: (self.item_height as f32 - cell_width / 2.0).max(0.0),
+		})
+	}
+
 	// TODO: Clean this up
 	fn position_of_item_at(&self, index: usize, position: Vector2) -> Vector2 {
+		// the item goes in the middle of its hex
+		if let Topology::Hex(layout) = self.topology {
+			let (x, y) = layout.center(self.cell_of_item(index), self.hex_spacing());
+			return position + Vector2 { x: x - self.item_width as f32 / 2.0, y: y - self.item_height as f32 / 2.0 };
+		}
+		// the bottom of the item sits on the bottom corner of its diamond, anything taller sticks up
+		if let Some(layout) = self.iso_layout() {
+			let (x, y) = layout.center(self.cell_of_item(index));
+			let bottom = y + layout.cell_width / 4.0;
+			return position + Vector2 { x: x - self.item_width as f32 / 2.0, y: bottom - self.item_height as f32 };
+		}
+
 		let (move_by, next_cycle) = if self.by_cols {
 			(
 				Vector2 { x: 0.0, y: (self.item_height + self.item_padding) as f32},
@@ -624,6 +764,9 @@ impl<T : DrawableObject> GridPanel<T> {
 			+ (next_cycle.scale_by((index % self.run_length) as f32))
 	}
 	
+	pub fn rec_of_item(&self, index: usize) -> Rectangle {
+		self.rec_of_item_at(index, self.get_position())
+	}
 	fn rec_of_item_at(&self, index: usize, position: Vector2) -> Rectangle {
 		let position = self.position_of_item_at(index, position);
 		Rectangle {
@@ -665,6 +808,41 @@ impl<T : DrawableObject> GridPanel<T> {
 
 		if colors.len() > 3 { panic!("Currently do not handle more than 3 colors in panel highlight") }
 	}
+
+	// Returns (rows, cols), for the hex and isometric math
+	fn grid_dimensions(&self) -> (usize, usize) {
+		let along = min(self.grid_array.len(), self.run_length);
+		let across = self.grid_array.len().div_ceil(self.run_length);
+		if self.by_cols { (across, along) } else { (along, across) }
+	}
+
+	// an outline around every hex, then each color as a smaller hex inside the last
+	fn draw_hex_highlights(&self, d: &mut RaylibDrawHandle, layout: HexLayout, rec: Rectangle, colors: &[Color]) {
+		let center = Vector2 { x: rec.x + rec.width / 2.0, y: rec.y + rec.height / 2.0 };
+		let radius = HexLayout::radius(self.hex_spacing());
+
+		for (i, color) in colors.iter().enumerate() {
+			d.draw_poly(center, 6, radius * (1.0 - 0.2 * i as f32), layout.rotation(), color);
+		}
+		d.draw_poly_lines(center, 6, radius, layout.rotation(), HEX_OUTLINE_COLOR);
+	}
+
+	// same as the hexes, smaller diamonds inside an outline
+	fn draw_iso_highlights(&self, d: &mut RaylibDrawHandle, layout: IsoLayout, index: usize, position: Vector2, colors: &[Color]) {
+		let (x, y) = layout.center(self.cell_of_item(index));
+		let center = Vector2::new(x, y) + position;
+		let corners = layout.corners(self.cell_of_item(index)).map(|(x, y)| Vector2::new(x, y) + position);
+
+		for (i, color) in colors.iter().enumerate() {
+			let [top, right, bottom, left] = corners.map(|corner| center + (corner - center).scale_by(1.0 - 0.2 * i as f32));
+			// raylib wants them counter clockwise
+			d.draw_triangle(top, left, bottom, color);
+			d.draw_triangle(top, bottom, right, color);
+		}
+		for i in 0..corners.len() {
+			d.draw_line_ex(corners[i], corners[(i + 1) % corners.len()], 1.0, ISO_OUTLINE_COLOR);
+		}
+	}
 }
 
 impl<T : DrawableObject> PanelLike for GridPanel<T> {
@@ -684,11 +862,26 @@ impl<T : DrawableObject> PanelLike for GridPanel<T> {
 			
 			highlight_color  : Some(Color::ORANGE),
 			background_color : None,
+
+			topology         : Topology::Square,
+			projection       : Projection::TopDown,
 		}
 	}
 
-	fn width (&self) -> f32 { self.length_helper(self.by_cols, self.item_width) as f32 }
-	fn height(&self) -> f32 { self.length_helper(!self.by_cols, self.item_height) as f32 }
+	fn width (&self) -> f32 {
+		if let Some(layout) = self.iso_layout() { return layout.grid_size().0; }
+		match self.topology {
+			Topology::Square => self.length_helper(self.by_cols, self.item_width) as f32,
+			Topology::Hex(layout) => layout.grid_size(self.grid_dimensions(), self.hex_spacing()).0,
+		}
+	}
+	fn height(&self) -> f32 {
+		if let Some(layout) = self.iso_layout() { return layout.grid_size().1; }
+		match self.topology {
+			Topology::Square => self.length_helper(!self.by_cols, self.item_height) as f32,
+			Topology::Hex(layout) => layout.grid_size(self.grid_dimensions(), self.hex_spacing()).1,
+		}
+	}
 
 	// TODO: change item padding to fit into new width. for pros only
 	fn set_width (&mut self, _width : f32) { todo!() }
@@ -698,6 +891,25 @@ impl<T : DrawableObject> PanelLike for GridPanel<T> {
 	fn set_drag_context(&mut self, drag_context: PanelUiDragContext) { self.drag_context = drag_context; }
 
 	fn get_hovered_id_at(&self, mouse_context: &MouseContext, position: Vector2) -> Option<usize> {
+		// the hexes cover everything, so its just whichever middle is closest.
+		// only the diamonds count for isometric, not the bits of tall tiles sticking up
+		let point = mouse_context.mouse_pos - position;
+		let hex_or_iso_cell = match (self.topology, self.iso_layout()) {
+			(Topology::Hex(layout), _) => Some(layout.cell_at((point.x, point.y), self.hex_spacing())),
+			(_, Some(layout)) => Some(layout.cell_at((point.x, point.y))),
+			_ => None,
+		};
+		if let Some((x, y)) = hex_or_iso_cell {
+			if !self.mouse_over_panel_at(mouse_context, position) { return None; }
+
+			let (rows, cols) = self.grid_dimensions();
+			if x < 0 || y < 0 || x as usize >= cols || y as usize >= rows { return None; }
+
+			let (x, y) = (x as usize, y as usize);
+			let index = if self.by_cols { y * self.run_length + x } else { x * self.run_length + y };
+			return if index < self.grid_array.len() { Some(index) } else { None };
+		}
+
 		for i in 0..self.grid_array.len() {
 			let rec = self.rec_of_item_at(i, position);
 
@@ -718,9 +930,14 @@ impl<T : DrawableObject> PanelLike for GridPanel<T> {
 
 		let hovered = self.get_hovered_id_at(mouse_context, position);
 
-		for (i, drawable) in self.grid_array.iter().enumerate() {
-			if drawable.is_none() { continue; }
-			let drawable = drawable.as_ref().unwrap();
+		// back to front for isometric, tiles in front can be tall enough to cover the ones behind
+		let mut draw_order: Vec<usize> = (0..self.grid_array.len()).collect();
+		if self.iso_layout().is_some() {
+			draw_order.sort_by_key(|i| IsoLayout::depth(self.cell_of_item(*i)));
+		}
+
+		for i in draw_order {
+			let Some(drawable) = self.grid_array[i].as_ref() else { continue; };
 
 			let rec = self.rec_of_item_at(i, position);
 
@@ -731,7 +948,11 @@ impl<T : DrawableObject> PanelLike for GridPanel<T> {
 				}
 			}
 
-			self.draw_highlights(d, rec, &highlights);
+			match (self.topology, self.iso_layout()) {
+				(Topology::Hex(layout), _) => self.draw_hex_highlights(d, layout, rec, &highlights),
+				(_, Some(layout)) => self.draw_iso_highlights(d, layout, i, position, &highlights),
+				_ => self.draw_highlights(d, rec, &highlights),
+			}
 
 			drawable.draw(d, rec);
 		}
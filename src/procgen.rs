@@ -0,0 +1,283 @@
+// quick level generators for blocking things out. caves grow out of noise with a cellular automaton,
+// dungeons cut the grid up with a bsp tree and put a room in each piece.
+// they only know walls and floors, which tiles those are is up to whoever calls them.
+// the same seed and settings always give the same grid
+
+use crate::tile_grid::*;
+use crate::random::SeededRng;
+use crate::walkability::walkable_areas;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaveSettings {
+    // how much starts out as wall, 0.45 is 45%
+    pub fill_chance: f64,
+    // rounds of smoothing, more gives rounder caves
+    pub steps: usize,
+    // a floor cell with at least this many walls around it (out of 8) turns into wall
+    pub birth_limit: usize,
+    // a wall cell with at least this many walls around it stays a wall
+    pub survive_limit: usize,
+    // fills in the pockets that can't reach the biggest cave
+    pub keep_largest: bool,
+}
+
+impl Default for CaveSettings {
+    fn default() -> Self {
+        CaveSettings { fill_chance: 0.45, steps: 4, birth_limit: 5, survive_limit: 4, keep_largest: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DungeonSettings {
+    // pieces don't get split smaller than this across, walls included
+    pub min_leaf: usize,
+    // rooms are at least this wide and tall, floor only
+    pub min_room: usize,
+    // stops splitting after this many levels, even if there's room
+    pub max_depth: usize,
+}
+
+impl Default for DungeonSettings {
+    fn default() -> Self {
+        DungeonSettings { min_leaf: 8, min_room: 3, max_depth: 6 }
+    }
+}
+
+// true for walls. the edge is always wall so the caves are closed in
+pub fn cave_walls(rows: usize, cols: usize, settings: CaveSettings, seed: u64) -> TileGrid<bool> {
+    let mut rng = SeededRng::new(seed);
+    let mut walls = TileGrid::new(rows, cols);
+    let is_edge = |(x, y): (usize, usize)| x == 0 || y == 0 || x + 1 == cols || y + 1 == rows;
+
+    for y in 0..rows {
+        for x in 0..cols {
+            // rolls for the edge too, so a cell's roll doesn't depend on the grid being bigger
+            let roll = rng.chance(settings.fill_chance);
+            walls.set((x, y), Some(roll || is_edge((x, y))));
+        }
+    }
+
+    for _ in 0..settings.steps {
+        let mut next = TileGrid::new(rows, cols);
+        for y in 0..rows {
+            for x in 0..cols {
+                // off the grid counts as wall, it keeps the edges from eating in
+                let neighbors = walls.neighbors((x, y), Connectivity::Eight);
+                let count = neighbors.iter().filter(|pos| walls.get(**pos) == &Some(true)).count() + (8 - neighbors.len());
+
+                let wall = if walls.get((x, y)) == &Some(true) { count >= settings.survive_limit } else { count >= settings.birth_limit };
+                next.set((x, y), Some(wall || is_edge((x, y))));
+            }
+        }
+        walls = next;
+    }
+
+    if settings.keep_largest {
+        let mut open = TileGrid::new(rows, cols);
+        for y in 0..rows {
+            for x in 0..cols {
+                open.set((x, y), Some(walls.get((x, y)) == &Some(false)));
+            }
+        }
+        for area in walkable_areas(&open, Connectivity::Four).iter().skip(1) {
+            for pos in area {
+                walls.set(*pos, Some(true));
+            }
+        }
+    }
+
+    return walls;
+}
+
+// (x, y, width, height)
+type Rect = (usize, usize, usize, usize);
+
+fn rect_center(rect: Rect) -> (usize, usize) {
+    (rect.0 + rect.2 / 2, rect.1 + rect.3 / 2)
+}
+
+// cuts leaf in two until it's too small or deep enough, carving a room into every piece.
+// Returns one of the rooms, for the corridor that joins this piece to its sibling
+fn split(walls: &mut TileGrid<bool>, leaf: Rect, depth: usize, settings: DungeonSettings, rng: &mut SeededRng) -> Option<Rect> {
+    let (x, y, width, height) = leaf;
+    // a room has to fit with a wall around it
+    let min_leaf = settings.min_leaf.max(settings.min_room + 2);
+    let can_split_x = width >= min_leaf * 2;
+    let can_split_y = height >= min_leaf * 2;
+
+    if depth < settings.max_depth && (can_split_x || can_split_y) {
+        // long pieces get cut across, so the rooms don't end up as hallways
+        let split_x = match (can_split_x, can_split_y) {
+            (true, false) => true,
+            (false, true) => false,
+            _ if width * 4 > height * 5 => true,
+            _ if height * 4 > width * 5 => false,
+            _ => rng.chance(0.5),
+        };
+
+        let (first, second) = if split_x {
+            let at = rng.range(min_leaf, width - min_leaf);
+            ((x, y, at, height), (x + at, y, width - at, height))
+        } else {
+            let at = rng.range(min_leaf, height - min_leaf);
+            ((x, y, width, at), (x, y + at, width, height - at))
+        };
+
+        let first_room = split(walls, first, depth + 1, settings, rng);
+        let second_room = split(walls, second, depth + 1, settings, rng);
+        if let (Some(a), Some(b)) = (first_room, second_room) {
+            carve_corridor(walls, rect_center(a), rect_center(b), rng);
+        }
+        return if rng.chance(0.5) { first_room.or(second_room) } else { second_room.or(first_room) };
+    }
+
+    // too small for a room, the grid must be tiny
+    if width < 3 || height < 3 { return None; }
+    let room_width = rng.range(settings.min_room.min(width - 2), width - 2);
+    let room_height = rng.range(settings.min_room.min(height - 2), height - 2);
+    let room_x = x + 1 + rng.range(0, width - 2 - room_width);
+    let room_y = y + 1 + rng.range(0, height - 2 - room_height);
+
+    for room_y in room_y..room_y + room_height {
+        for room_x in room_x..room_x + room_width {
+            walls.set((room_x, room_y), Some(false));
+        }
+    }
+    return Some((room_x, room_y, room_width, room_height));
+}
+
+// an L from a to b, which way it bends is up to the rng
+fn carve_corridor(walls: &mut TileGrid<bool>, a: (usize, usize), b: (usize, usize), rng: &mut SeededRng) {
+    let corner = if rng.chance(0.5) { (b.0, a.1) } else { (a.0, b.1) };
+
+    for (from, to) in [(a, corner), (corner, b)] {
+        for y in from.1.min(to.1)..=from.1.max(to.1) {
+            for x in from.0.min(to.0)..=from.0.max(to.0) {
+                walls.set((x, y), Some(false));
+            }
+        }
+    }
+}
+
+// true for walls. every room can reach every other one
+pub fn dungeon_walls(rows: usize, cols: usize, settings: DungeonSettings, seed: u64) -> TileGrid<bool> {
+    let mut rng = SeededRng::new(seed);
+    let mut walls = TileGrid::new(rows, cols);
+    for i in 0..rows * cols {
+        walls.set(index_to_pos(i, walls.size()), Some(true));
+    }
+
+    split(&mut walls, (0, 0, cols, rows), 0, settings, &mut rng);
+    return walls;
+}
+
+// swaps the walls and floors for real tiles. None leaves the cells empty
+pub fn paint_walls<T: Clone>(walls: &TileGrid<bool>, wall: Option<T>, floor: Option<T>) -> TileGrid<T> {
+    let mut grid = TileGrid::new(walls.rows, walls.cols);
+    for i in 0..walls.rows * walls.cols {
+        let pos = index_to_pos(i, walls.size());
+        grid.set(pos, if walls.get(pos) == &Some(true) { wall.clone() } else { floor.clone() });
+    }
+    return grid;
+}
+
+pub fn generate_cave<T: Clone>(rows: usize, cols: usize, wall: Option<T>, floor: Option<T>, settings: CaveSettings, seed: u64) -> TileGrid<T> {
+    paint_walls(&cave_walls(rows, cols, settings, seed), wall, floor)
+}
+
+pub fn generate_dungeon<T: Clone>(rows: usize, cols: usize, wall: Option<T>, floor: Option<T>, settings: DungeonSettings, seed: u64) -> TileGrid<T> {
+    paint_walls(&dungeon_walls(rows, cols, settings, seed), wall, floor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn show(walls: &TileGrid<bool>) -> Vec<String> {
+        paint_walls(walls, Some("#".to_owned()), Some(".".to_owned())).to_rows()
+    }
+
+    fn floors(walls: &TileGrid<bool>) -> TileGrid<bool> {
+        paint_walls(walls, Some(false), Some(true))
+    }
+
+    fn edges_are_walls(walls: &TileGrid<bool>) -> bool {
+        (0..walls.rows * walls.cols)
+            .map(|i| index_to_pos(i, walls.size()))
+            .filter(|(x, y)| *x == 0 || *y == 0 || x + 1 == walls.cols || y + 1 == walls.rows)
+            .all(|pos| walls.get(pos) == &Some(true))
+    }
+
+    // if these change on purpose, everyone's saved seeds make different levels now
+    #[test]
+    fn cave_seed_snapshot() {
+        assert_eq!(show(&cave_walls(12, 20, CaveSettings::default(), 7)), vec![
+            "####################",
+            "####################",
+            "######...###########",
+            "#####.....###...####",
+            "#####............###",
+            "####.............###",
+            "####............####",
+            "####............####",
+            "####...........#####",
+            "#####........#######",
+            "####################",
+            "####################",
+        ]);
+    }
+
+    #[test]
+    fn dungeon_seed_snapshot() {
+        assert_eq!(show(&dungeon_walls(12, 20, DungeonSettings::default(), 7)), vec![
+            "####################",
+            "###...#####....#####",
+            "###...#####....#####",
+            "###...#####....#####",
+            "###...#####....#####",
+            "###...#####....#####",
+            "###............#####",
+            "###...##############",
+            "###...##############",
+            "###...##############",
+            "###...##############",
+            "####################",
+        ]);
+    }
+
+    #[test]
+    fn same_seed_same_level() {
+        for seed in 0..5 {
+            assert_eq!(cave_walls(30, 40, CaveSettings::default(), seed), cave_walls(30, 40, CaveSettings::default(), seed));
+            assert_eq!(dungeon_walls(30, 40, DungeonSettings::default(), seed), dungeon_walls(30, 40, DungeonSettings::default(), seed));
+        }
+        assert_ne!(show(&cave_walls(30, 40, CaveSettings::default(), 1)), show(&cave_walls(30, 40, CaveSettings::default(), 2)));
+    }
+
+    #[test]
+    fn dungeon_floor_is_all_connected() {
+        for seed in 0..20 {
+            let walls = dungeon_walls(40, 60, DungeonSettings::default(), seed);
+            let areas = walkable_areas(&floors(&walls), Connectivity::Four);
+            assert_eq!(areas.len(), 1, "seed {} has {} separate areas", seed, areas.len());
+            assert!(edges_are_walls(&walls), "seed {} has a hole in the edge", seed);
+        }
+    }
+
+    #[test]
+    fn cave_keeps_only_the_largest_area() {
+        for seed in 0..20 {
+            let walls = cave_walls(40, 60, CaveSettings::default(), seed);
+            assert!(walkable_areas(&floors(&walls), Connectivity::Four).len() <= 1, "seed {}", seed);
+            assert!(edges_are_walls(&walls), "seed {} has a hole in the edge", seed);
+        }
+    }
+
+    #[test]
+    fn tiny_grids_dont_panic() {
+        for (rows, cols) in [(0, 0), (1, 1), (2, 5), (3, 3)] {
+            assert_eq!(dungeon_walls(rows, cols, DungeonSettings::default(), 3).size(), (rows, cols));
+            assert_eq!(cave_walls(rows, cols, CaveSettings::default(), 3).size(), (rows, cols));
+        }
+    }
+}
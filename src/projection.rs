@@ -0,0 +1,214 @@
+// how a square grid gets put on the screen. isometric turns every cell into a diamond
+// twice as wide as it is tall, the grid is stored the same way either way
+
+use json::JsonValue;
+
+use crate::tile_grid::*;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Projection {
+    #[default]
+    TopDown,
+    // x goes down and to the right, y goes down and to the left
+    Isometric,
+}
+
+impl Projection {
+    pub fn next(&self) -> Projection {
+        match self {
+            Projection::TopDown   => Projection::Isometric,
+            Projection::Isometric => Projection::TopDown,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Projection::TopDown   => "top down",
+            Projection::Isometric => "isometric",
+        }
+    }
+}
+
+impl ToAndFromJsonValue for Projection {
+    fn to_json(&self) -> JsonValue {
+        match self {
+            Projection::TopDown   => "top_down".into(),
+            Projection::Isometric => "isometric".into(),
+        }
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        match source.as_str()? {
+            "top_down"  => Some(Projection::TopDown),
+            "isometric" => Some(Projection::Isometric),
+            _ => None,
+        }
+    }
+}
+
+// where a cell goes depends on how many rows there are, since row 0 starts in the middle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IsoLayout {
+    // (rows, cols)
+    pub size: (usize, usize),
+    // corner to corner, left to right. the diamond is half as tall
+    pub cell_width: f32,
+    // room at the top for tiles taller than the diamond, so the back row doesn't poke out of the panel
+    pub overhang: f32,
+}
+
+impl IsoLayout {
+    // Returns (half width, half height) of a diamond
+    fn half(&self) -> (f32, f32) {
+        (self.cell_width / 2.0, self.cell_width / 4.0)
+    }
+
+    // a point in cells to pixels from the top left of the grid.
+    // (0, 0) is the top corner of the first cell and (1, 1) is its bottom corner
+    pub fn point_to_screen(&self, point: (f32, f32)) -> (f32, f32) {
+        let (half_width, half_height) = self.half();
+        (
+            self.size.0 as f32 * half_width + (point.0 - point.1) * half_width,
+            self.overhang + (point.0 + point.1) * half_height,
+        )
+    }
+
+    // the other way around, might be off the grid
+    pub fn screen_to_point(&self, screen: (f32, f32)) -> (f32, f32) {
+        let (half_width, half_height) = self.half();
+        let across = (screen.0 - self.size.0 as f32 * half_width) / half_width;
+        let down = (screen.1 - self.overhang) / half_height;
+        ((down + across) / 2.0, (down - across) / 2.0)
+    }
+
+    // which diamond a point is in, might be off the grid
+    pub fn cell_at(&self, screen: (f32, f32)) -> (i64, i64) {
+        let (x, y) = self.screen_to_point(screen);
+        (x.floor() as i64, y.floor() as i64)
+    }
+
+    pub fn center(&self, pos: (usize, usize)) -> (f32, f32) {
+        self.point_to_screen((pos.0 as f32 + 0.5, pos.1 as f32 + 0.5))
+    }
+
+    // top, right, bottom, left
+    pub fn corners(&self, pos: (usize, usize)) -> [(f32, f32); 4] {
+        let (x, y) = (pos.0 as f32, pos.1 as f32);
+        [
+            self.point_to_screen((x, y)),
+            self.point_to_screen((x + 1.0, y)),
+            self.point_to_screen((x + 1.0, y + 1.0)),
+            self.point_to_screen((x, y + 1.0)),
+        ]
+    }
+
+    // Returns (width, height) of the whole grid in pixels
+    pub fn grid_size(&self) -> (f32, f32) {
+        let (half_width, half_height) = self.half();
+        let across = (self.size.0 + self.size.1) as f32;
+        (across * half_width, self.overhang + across * half_height)
+    }
+
+    // sort cells by this to draw them back to front, so tall tiles cover the ones behind them.
+    // cells with the same x + y sit side by side and never cover each other
+    pub fn depth(pos: (usize, usize)) -> (usize, usize) {
+        (pos.0 + pos.1, pos.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3 rows, 4 cols
+    const LAYOUT: IsoLayout = IsoLayout { size: (3, 4), cell_width: 32.0, overhang: 10.0 };
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 0.001 && (a.1 - b.1).abs() < 0.001
+    }
+
+    #[test]
+    fn screen_to_point_undoes_point_to_screen() {
+        for point in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (2.5, 1.25), (-1.0, 4.0), (3.9, 2.1)] {
+            let screen = LAYOUT.point_to_screen(point);
+            assert!(close(LAYOUT.screen_to_point(screen), point), "{:?}", point);
+        }
+        for screen in [(0.0, 0.0), (48.0, 10.0), (100.0, 57.5)] {
+            assert!(close(LAYOUT.point_to_screen(LAYOUT.screen_to_point(screen)), screen), "{:?}", screen);
+        }
+    }
+
+    #[test]
+    fn diamonds_are_twice_as_wide_as_tall() {
+        // the first cell's top corner is pushed right by a half diamond per row, under the overhang
+        let [top, right, bottom, left] = LAYOUT.corners((0, 0));
+        assert!(close(top, (48.0, 10.0)));
+        assert!(close(right, (64.0, 18.0)));
+        assert!(close(bottom, (48.0, 26.0)));
+        assert!(close(left, (32.0, 18.0)));
+        assert!(close(LAYOUT.center((0, 0)), (48.0, 18.0)));
+    }
+
+    #[test]
+    fn the_whole_grid_fits_in_grid_size() {
+        let (width, height) = LAYOUT.grid_size();
+        let (rows, cols) = LAYOUT.size;
+        assert!(close(LAYOUT.point_to_screen((0.0, rows as f32)), (0.0, LAYOUT.overhang + rows as f32 * 8.0)));
+        assert!(close(LAYOUT.point_to_screen((cols as f32, 0.0)), (width, LAYOUT.overhang + cols as f32 * 8.0)));
+        assert!(close(LAYOUT.point_to_screen((cols as f32, rows as f32)), (cols as f32 * 16.0, height)));
+    }
+
+    #[test]
+    fn cell_at_finds_the_cell_up_to_its_edges() {
+        for pos in [(0, 0), (1, 2), (3, 1)] {
+            let center = LAYOUT.center(pos);
+            let cell = (pos.0 as i64, pos.1 as i64);
+            assert_eq!(LAYOUT.cell_at(center), cell);
+
+            // the middle of each edge, and the cell on the other side of it
+            let [top, right, bottom, left] = LAYOUT.corners(pos);
+            let edges = [
+                (top, right, (cell.0, cell.1 - 1)),
+                (right, bottom, (cell.0 + 1, cell.1)),
+                (bottom, left, (cell.0, cell.1 + 1)),
+                (left, top, (cell.0 - 1, cell.1)),
+            ];
+            for (from, to, across) in edges {
+                let middle = ((from.0 + to.0) / 2.0, (from.1 + to.1) / 2.0);
+                let toward = |amount: f32| (middle.0 + (center.0 - middle.0) * amount, middle.1 + (center.1 - middle.1) * amount);
+                assert_eq!(LAYOUT.cell_at(toward(0.05)), cell, "{:?}", pos);
+                assert_eq!(LAYOUT.cell_at(toward(-0.05)), across, "{:?}", pos);
+            }
+        }
+    }
+
+    #[test]
+    fn cells_in_front_are_drawn_later() {
+        let (rows, cols) = LAYOUT.size;
+        let mut cells: Vec<(usize, usize)> = (0..rows).flat_map(|y| (0..cols).map(move |x| (x, y))).collect();
+        cells.sort_by_key(|pos| IsoLayout::depth(*pos));
+
+        for (index, pos) in cells.iter().enumerate() {
+            let drawn_after = &cells[index + 1..];
+            // the cells below right and below left are in front of this one
+            for in_front in [(pos.0 + 1, pos.1), (pos.0, pos.1 + 1)] {
+                if in_front.0 < cols && in_front.1 < rows {
+                    assert!(drawn_after.contains(&in_front), "{:?} before {:?}", pos, in_front);
+                }
+            }
+        }
+
+        // and never drawn above something that's further back on screen
+        for pair in cells.windows(2) {
+            assert!(LAYOUT.center(pair[0]).1 <= LAYOUT.center(pair[1]).1);
+        }
+    }
+
+    #[test]
+    fn projection_json_round_trips() {
+        for projection in [Projection::TopDown, Projection::Isometric] {
+            assert_eq!(Projection::from_json(&projection.to_json()), Some(projection));
+        }
+        assert_eq!(Projection::from_json(&"oblique".into()), None);
+    }
+}
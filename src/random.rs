@@ -0,0 +1,56 @@
+// seeded random numbers for the generators, so the same seed always gives the same map.
+// splitmix64, it's tiny and plenty random for picking tiles
+
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // 0 up to but not including 1
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // 0 up to but not including n, n can't be 0
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "nothing to pick from");
+        (self.next_f64() * n as f64) as usize
+    }
+
+    // min up to and including max
+    pub fn range(&mut self, min: usize, max: usize) -> usize {
+        assert!(min <= max, "empty range");
+        min + self.below(max - min + 1)
+    }
+
+    // true about probability of the time, 0.45 is 45%
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+
+    // an index into weights, picked in proportion to them. they can't all be 0
+    pub fn weighted(&mut self, weights: &[f64]) -> usize {
+        let total: f64 = weights.iter().sum();
+        let mut roll = self.next_f64() * total;
+
+        for (index, weight) in weights.iter().enumerate() {
+            if roll < *weight { return index; }
+            roll -= weight;
+        }
+        // float error can leave a little over, it goes to the last one that could be picked
+        return weights.iter().rposition(|weight| *weight > 0.0).expect("some weight");
+    }
+}
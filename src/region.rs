@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use json::{object, JsonValue};
+
+use crate::tile_grid::*;
+use crate::tile_definition::TileProperty;
+
+// a named box of cells on top of the tiles, for spawn zones, triggers, biomes, ect.
+// regions don't care what layer they're on, they belong to the whole floor
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub name: String,
+    // rgb, it gets drawn see through
+    pub color: (u8, u8, u8),
+    // corners, inclusive
+    pub min: (usize, usize),
+    pub max: (usize, usize),
+    // same types as tile properties, so a game can read both the same way
+    pub properties: BTreeMap<String, TileProperty>,
+}
+
+impl Region {
+    // the corners can be any two opposite ones
+    pub fn new(name: &str, color: (u8, u8, u8), start: (usize, usize), end: (usize, usize)) -> Self {
+        let mut region = Region { name: name.to_owned(), color, min: start, max: end, properties: BTreeMap::new() };
+        region.set_corners(start, end);
+        return region;
+    }
+
+    pub fn set_corners(&mut self, start: (usize, usize), end: (usize, usize)) {
+        self.min = (start.0.min(end.0), start.1.min(end.1));
+        self.max = (start.0.max(end.0), start.1.max(end.1));
+    }
+
+    pub fn contains(&self, pos: (usize, usize)) -> bool {
+        self.min.0 <= pos.0 && pos.0 <= self.max.0 && self.min.1 <= pos.1 && pos.1 <= self.max.1
+    }
+
+    // clockwise from the top left
+    pub fn corners(&self) -> [(usize, usize); 4] {
+        [self.min, (self.max.0, self.min.1), self.max, (self.min.0, self.max.1)]
+    }
+
+    // the corner across from one of the corners() ones
+    pub fn opposite_corner(&self, corner: (usize, usize)) -> (usize, usize) {
+        let x = if corner.0 == self.min.0 { self.max.0 } else { self.min.0 };
+        let y = if corner.1 == self.min.1 { self.max.1 } else { self.min.1 };
+        return (x, y);
+    }
+
+    // the part of it that's still on the grid after the edges move.
+    // Returns None if all of it got cut off
+    pub fn resized(&self, edges: EdgeDeltas, old_size: (usize, usize)) -> Option<Region> {
+        let (rows, cols) = edges.new_size(old_size);
+        if rows == 0 || cols == 0 { return None; }
+
+        let min_x = (self.min.0 as i64 + edges.left).max(0);
+        let min_y = (self.min.1 as i64 + edges.top ).max(0);
+        let max_x = (self.max.0 as i64 + edges.left).min(cols as i64 - 1);
+        let max_y = (self.max.1 as i64 + edges.top ).min(rows as i64 - 1);
+        if min_x > max_x || min_y > max_y { return None; }
+
+        let mut region = self.clone();
+        region.min = (min_x as usize, min_y as usize);
+        region.max = (max_x as usize, max_y as usize);
+        return Some(region);
+    }
+
+    pub fn transformed(&self, transform: GridTransform, old_size: (usize, usize)) -> Region {
+        let mut region = self.clone();
+        region.set_corners(transform.move_pos(self.min, old_size), transform.move_pos(self.max, old_size));
+        return region;
+    }
+}
+
+impl ToAndFromJsonValue for Region {
+    fn to_json(&self) -> JsonValue {
+        let mut json_object = object! {
+            name: self.name.clone(),
+            color: [self.color.0, self.color.1, self.color.2],
+            min: format!("({},{})", self.min.0, self.min.1),
+            max: format!("({},{})", self.max.0, self.max.1),
+            properties: {},
+        };
+
+        for (name, property) in self.properties.iter() {
+            json_object["properties"][name.as_str()] = property.to_json();
+        }
+
+        return json_object;
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        let color = &source["color"];
+        let mut region = Region::new(
+            source["name"].as_str()?,
+            (color[0].as_u8()?, color[1].as_u8()?, color[2].as_u8()?),
+            parse_pos_key(source["min"].as_str()?)?,
+            parse_pos_key(source["max"].as_str()?)?,
+        );
+
+        for (name, property) in source["properties"].entries() {
+            region.properties.insert(name.to_owned(), TileProperty::from_json(property)?);
+        }
+
+        return Some(region);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(start: (usize, usize), end: (usize, usize)) -> Region {
+        Region::new("zone", (200, 40, 40), start, end)
+    }
+
+    #[test]
+    fn corners_can_be_given_either_way_around() {
+        let from_bottom_left = region((1, 4), (3, 2));
+        assert_eq!(from_bottom_left.min, (1, 2));
+        assert_eq!(from_bottom_left.max, (3, 4));
+        assert_eq!(from_bottom_left, region((3, 4), (1, 2)));
+
+        assert_eq!(from_bottom_left.corners(), [(1, 2), (3, 2), (3, 4), (1, 4)]);
+        for corner in from_bottom_left.corners() {
+            let opposite = from_bottom_left.opposite_corner(corner);
+            assert_eq!(region(corner, opposite), from_bottom_left);
+        }
+    }
+
+    #[test]
+    fn contains_includes_the_edges() {
+        let zone = region((1, 1), (3, 2));
+        for pos in [(1, 1), (3, 1), (1, 2), (3, 2), (2, 2)] {
+            assert!(zone.contains(pos), "{:?}", pos);
+        }
+        for pos in [(0, 1), (4, 1), (1, 0), (1, 3), (0, 0)] {
+            assert!(!zone.contains(pos), "{:?}", pos);
+        }
+        assert!(region((2, 2), (2, 2)).contains((2, 2)));
+    }
+
+    #[test]
+    fn overlapping_regions_both_contain_the_shared_cells() {
+        let regions = [region((0, 0), (2, 2)), region((2, 1), (4, 3))];
+        let holding = |pos| regions.iter().enumerate().filter(|(_, region)| region.contains(pos)).map(|(index, _)| index).collect::<Vec<_>>();
+
+        assert_eq!(holding((2, 1)), vec![0, 1]);
+        assert_eq!(holding((2, 2)), vec![0, 1]);
+        assert_eq!(holding((1, 1)), vec![0]);
+        assert_eq!(holding((3, 3)), vec![1]);
+        assert_eq!(holding((3, 0)), Vec::<usize>::new());
+        // the editor picks the one drawn on top, which is the later one
+        assert_eq!(regions.iter().rposition(|region| region.contains((2, 2))), Some(1));
+    }
+
+    #[test]
+    fn resizing_moves_and_clips_regions() {
+        let zone = region((1, 1), (3, 2));
+        let old_size = (4, 5);
+
+        // two cols on the left push it right
+        let grown = zone.resized(EdgeDeltas { left: 2, right: 0, top: 0, bottom: 0 }, old_size).expect("Still on the grid");
+        assert_eq!((grown.min, grown.max), ((3, 1), (5, 2)));
+
+        // losing two cols on the right cuts the end off
+        let clipped = zone.resized(EdgeDeltas { left: 0, right: -2, top: 0, bottom: 0 }, old_size).expect("Still on the grid");
+        assert_eq!((clipped.min, clipped.max), ((1, 1), (2, 2)));
+
+        // and losing the top three rows leaves nothing
+        assert_eq!(zone.resized(EdgeDeltas { left: 0, right: 0, top: -3, bottom: 0 }, old_size), None);
+    }
+
+    #[test]
+    fn transforms_move_regions_with_the_grid() {
+        // 2 rows, 3 cols, the region is the whole top row
+        let top_row = region((0, 0), (2, 0));
+        let rotated = top_row.transformed(GridTransform::Rotate90, (2, 3));
+        // after a turn clockwise the top row is the right col
+        assert_eq!((rotated.min, rotated.max), ((1, 0), (1, 2)));
+
+        let flipped = top_row.transformed(GridTransform::FlipVertical, (2, 3));
+        assert_eq!((flipped.min, flipped.max), ((0, 1), (2, 1)));
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let mut zone = region((4, 1), (2, 3));
+        zone.properties.insert("spawns".to_owned(), TileProperty::Int(3));
+        assert_eq!(Region::from_json(&zone.to_json()), Some(zone));
+    }
+}
@@ -0,0 +1,163 @@
+// turning two corners into the cells a shape covers. nothing in here knows
+// about grids or raylib, every cell returned is inside the box start and end make
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Line,
+    Rect,
+    FilledRect,
+    Ellipse,
+}
+
+impl Shape {
+    pub const ALL: [Shape; 4] = [Shape::Line, Shape::Rect, Shape::FilledRect, Shape::Ellipse];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shape::Line       => "Line",
+            Shape::Rect       => "Rect",
+            Shape::FilledRect => "Filled Rect",
+            Shape::Ellipse    => "Ellipse",
+        }
+    }
+
+    // the one after this, wraps around
+    pub fn next(&self) -> Shape {
+        let index = Shape::ALL.iter().position(|shape| shape == self).expect("Shape is in ALL");
+        return Shape::ALL[(index + 1) % Shape::ALL.len()];
+    }
+
+    pub fn cells(&self, start: (usize, usize), end: (usize, usize)) -> Vec<(usize, usize)> {
+        match self {
+            Shape::Line       => line(start, end),
+            Shape::Rect       => rect_outline(start, end),
+            Shape::FilledRect => rect_filled(start, end),
+            Shape::Ellipse    => ellipse(start, end),
+        }
+    }
+}
+
+// Returns (min x, min y, max x, max y), inclusive
+pub fn bounding_box(start: (usize, usize), end: (usize, usize)) -> (usize, usize, usize, usize) {
+    (start.0.min(end.0), start.1.min(end.1), start.0.max(end.0), start.1.max(end.1))
+}
+
+// bresenham, goes from start to end with no gaps and no doubled up corners
+pub fn line(start: (usize, usize), end: (usize, usize)) -> Vec<(usize, usize)> {
+    let (mut x, mut y) = (start.0 as i64, start.1 as i64);
+    let (end_x, end_y) = (end.0 as i64, end.1 as i64);
+
+    let dx =  (end_x - x).abs();
+    let dy = -(end_y - y).abs();
+    let step_x = if x < end_x { 1 } else { -1 };
+    let step_y = if y < end_y { 1 } else { -1 };
+    let mut error = dx + dy;
+
+    let mut cells = vec![];
+    loop {
+        cells.push((x as usize, y as usize));
+        if x == end_x && y == end_y { break; }
+
+        let doubled = error * 2;
+        if doubled >= dy { error += dy; x += step_x; }
+        if doubled <= dx { error += dx; y += step_y; }
+    }
+
+    return cells;
+}
+
+pub fn rect_filled(start: (usize, usize), end: (usize, usize)) -> Vec<(usize, usize)> {
+    let (min_x, min_y, max_x, max_y) = bounding_box(start, end);
+
+    let mut cells = vec![];
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            cells.push((x, y));
+        }
+    }
+    return cells;
+}
+
+pub fn rect_outline(start: (usize, usize), end: (usize, usize)) -> Vec<(usize, usize)> {
+    let (min_x, min_y, max_x, max_y) = bounding_box(start, end);
+
+    rect_filled(start, end)
+        .into_iter()
+        .filter(|&(x, y)| x == min_x || x == max_x || y == min_y || y == max_y)
+        .collect()
+}
+
+// the ellipse that fits in the box. a cell is inside if its center is,
+// and it's on the outline if it touches a cell that isn't
+pub fn ellipse(start: (usize, usize), end: (usize, usize)) -> Vec<(usize, usize)> {
+    let (min_x, min_y, max_x, max_y) = bounding_box(start, end);
+
+    let radius_x = (max_x - min_x + 1) as f64 / 2.0;
+    let radius_y = (max_y - min_y + 1) as f64 / 2.0;
+    let center_x = min_x as f64 + radius_x;
+    let center_y = min_y as f64 + radius_y;
+
+    let inside = |x: i64, y: i64| {
+        if x < min_x as i64 || x > max_x as i64 || y < min_y as i64 || y > max_y as i64 { return false; }
+
+        let nx = (x as f64 + 0.5 - center_x) / radius_x;
+        let ny = (y as f64 + 0.5 - center_y) / radius_y;
+        return nx * nx + ny * ny <= 1.0;
+    };
+
+    rect_filled(start, end)
+        .into_iter()
+        .filter(|&(x, y)| {
+            let (x, y) = (x as i64, y as i64);
+            inside(x, y) && !(inside(x - 1, y) && inside(x + 1, y) && inside(x, y - 1) && inside(x, y + 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_have_no_gaps_or_doubled_corners() {
+        assert_eq!(line((0, 0), (3, 1)), vec![(0, 0), (1, 0), (2, 1), (3, 1)]);
+        assert_eq!(line((2, 2), (2, 2)), vec![(2, 2)]);
+        assert_eq!(line((0, 4), (0, 0)), vec![(0, 4), (0, 3), (0, 2), (0, 1), (0, 0)]);
+
+        // backwards covers as many cells, and every step is to a touching cell
+        let cells = line((7, 5), (1, 2));
+        assert_eq!(cells.len(), line((1, 2), (7, 5)).len());
+        for pair in cells.windows(2) {
+            assert!(pair[0].0.abs_diff(pair[1].0) <= 1 && pair[0].1.abs_diff(pair[1].1) <= 1);
+        }
+    }
+
+    #[test]
+    fn rects_cover_the_box_either_way_round() {
+        assert_eq!(rect_filled((2, 1), (0, 0)).len(), 6);
+        assert_eq!(rect_outline((0, 0), (2, 2)).len(), 8);
+        assert!(!rect_outline((0, 0), (2, 2)).contains(&(1, 1)));
+        assert_eq!(rect_outline((3, 3), (0, 0)).len(), 12);
+        assert_eq!(rect_outline((1, 1), (1, 1)), vec![(1, 1)]);
+    }
+
+    #[test]
+    fn ellipses_stay_in_the_box_and_are_hollow() {
+        let cells = ellipse((0, 0), (6, 4));
+        assert!(cells.iter().all(|&(x, y)| x <= 6 && y <= 4));
+        // touches all four sides, but not the corners
+        assert!(cells.contains(&(3, 0)) && cells.contains(&(3, 4)) && cells.contains(&(0, 2)) && cells.contains(&(6, 2)));
+        assert!(!cells.contains(&(0, 0)) && !cells.contains(&(3, 2)));
+
+        // too thin to have a middle
+        assert_eq!(ellipse((0, 0), (0, 3)).len(), 4);
+        assert_eq!(ellipse((0, 0), (1, 1)).len(), 4);
+    }
+
+    #[test]
+    fn shapes_cycle_back_round() {
+        let mut shape = Shape::Line;
+        for _ in 0..Shape::ALL.len() { shape = shape.next(); }
+        assert_eq!(shape, Shape::Line);
+    }
+}
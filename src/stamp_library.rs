@@ -0,0 +1,100 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::tile_grid::*;
+
+// a saved piece of map, doorways and trees and stuff.
+// on disk its just a grid, "<name>.json" in the stamps folder
+#[derive(Debug, Clone)]
+pub struct Stamp<T> {
+    pub name: String,
+    pub grid: TileGrid<T>,
+}
+
+impl<T> Stamp<T>
+where
+    T: PartialEq,
+{
+    // Returns every item in the stamp that is_loaded says no to, once each
+    pub fn missing_tiles(&self, is_loaded: impl Fn(&T) -> bool) -> Vec<&T> {
+        let mut missing = vec![];
+        for item in self.grid.items() {
+            if !is_loaded(item) && !missing.contains(&item) {
+                missing.push(item);
+            }
+        }
+        return missing;
+    }
+}
+
+// names end up as file names, so keep them to one plain file
+pub fn valid_stamp_name(name: &str) -> bool {
+    !name.trim().is_empty() && !name.contains(['/', '\\']) && !name.starts_with('.')
+}
+
+// sorted by name. no folder just means no stamps yet
+pub fn load_stamps<T: ToAndFromJsonValue>(dir: &Path) -> Vec<Stamp<T>> {
+    let Ok(paths) = fs::read_dir(dir) else { return vec![]; };
+
+    let mut stamps: Vec<Stamp<T>> = paths
+        .map(|path| path.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str().expect("Valid path").to_owned();
+
+            let mut buffer = String::new();
+            fs::File::open(&path).expect("File exists").read_to_string(&mut buffer).expect("Read to buffer");
+
+            let Some(grid) = json::parse(&buffer).ok().and_then(|source| TileGrid::from_json(&source)) else {
+                println!("Stamp {name} is broken, skipping it");
+                return None;
+            };
+            Some(Stamp { name, grid })
+        })
+        .collect();
+
+    stamps.sort_by(|a, b| a.name.cmp(&b.name));
+    return stamps;
+}
+
+// writes over any stamp with the same name
+pub fn save_stamp<T: ToAndFromJsonValue>(dir: &Path, name: &str, grid: &TileGrid<T>) {
+    assert!(valid_stamp_name(name), "Stamp name is a plain file name");
+
+    fs::create_dir_all(dir).expect("Stamp folder was created");
+    let mut output = fs::File::create(dir.join(format!("{name}.json"))).expect("File was created");
+    write!(output, "{}", grid.to_json().pretty(4)).expect("Write to file");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_stamps_get_skipped() {
+        let dir = std::env::temp_dir().join(format!("tile_editor_stamps_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut grid: TileGrid<String> = TileGrid::new(1, 2);
+        grid.set((1, 0), Some("tree".to_owned()));
+        save_stamp(&dir, "tree", &grid);
+        fs::write(dir.join("not json.json"), "{ oops").unwrap();
+        fs::write(dir.join("not a grid.json"), r#"{ "version": "1.0", "rows": 1 }"#).unwrap();
+
+        let stamps: Vec<Stamp<String>> = load_stamps(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(stamps.len(), 1);
+        assert_eq!(stamps[0].name, "tree");
+        assert_eq!(stamps[0].grid.get((1, 0)).as_deref(), Some("tree"));
+    }
+
+    #[test]
+    fn stamp_names_stay_in_the_folder() {
+        assert!(valid_stamp_name("doorway"));
+        assert!(!valid_stamp_name("../doorway"));
+        assert!(!valid_stamp_name(".hidden"));
+        assert!(!valid_stamp_name("  "));
+    }
+}
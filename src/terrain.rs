@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use json::{object, JsonValue};
+
+use crate::tile_grid::*;
+
+// lives next to the palette manifest
+pub const TERRAIN_SETS_FILE: &str = "terrains.json";
+
+// neighbour bits, clockwise from the top. Edges4 only uses the four sides
+pub const NORTH     : u8 = 1;
+pub const NORTH_EAST: u8 = 2;
+pub const EAST      : u8 = 4;
+pub const SOUTH_EAST: u8 = 8;
+pub const SOUTH     : u8 = 16;
+pub const SOUTH_WEST: u8 = 32;
+pub const WEST      : u8 = 64;
+pub const NORTH_WEST: u8 = 128;
+
+const NEIGHBOUR_OFFSETS: [(u8, (i64, i64)); 8] = [
+    (NORTH,      ( 0, -1)),
+    (NORTH_EAST, ( 1, -1)),
+    (EAST,       ( 1,  0)),
+    (SOUTH_EAST, ( 1,  1)),
+    (SOUTH,      ( 0,  1)),
+    (SOUTH_WEST, (-1,  1)),
+    (WEST,       (-1,  0)),
+    (NORTH_WEST, (-1, -1)),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerrainKind {
+    // 16 tiles, one for every mix of the four sides
+    Edges4,
+    // 47 tiles, corners only count when both sides next to them are filled
+    Blob47,
+}
+
+// a set of icons that all count as the same terrain, with which one to use
+// for every arrangement of neighbours. masks are made from the bits above
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainSet {
+    pub name: String,
+    pub kind: TerrainKind,
+    pub tiles: BTreeMap<u8, String>,
+}
+
+// a corner with an open side next to it looks the same as an open corner,
+// dropping those is what takes 256 masks down to 47
+pub fn blob_mask(mask: u8) -> u8 {
+    let mut result = mask & (NORTH | EAST | SOUTH | WEST);
+    for (corner, side_a, side_b) in [
+        (NORTH_EAST, NORTH, EAST),
+        (SOUTH_EAST, SOUTH, EAST),
+        (SOUTH_WEST, SOUTH, WEST),
+        (NORTH_WEST, NORTH, WEST),
+    ] {
+        if mask & corner != 0 && mask & side_a != 0 && mask & side_b != 0 {
+            result |= corner;
+        }
+    }
+    return result;
+}
+
+impl TerrainKind {
+    // the masks a complete set needs a tile for
+    pub fn masks(&self) -> Vec<u8> {
+        let mut masks: Vec<u8> = match self {
+            TerrainKind::Edges4 => (0..=255u8).map(|mask| mask & (NORTH | EAST | SOUTH | WEST)).collect(),
+            TerrainKind::Blob47 => (0..=255u8).map(blob_mask).collect(),
+        };
+        masks.sort();
+        masks.dedup();
+        return masks;
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            TerrainKind::Edges4 => "edges4",
+            TerrainKind::Blob47 => "blob47",
+        }
+    }
+}
+
+impl TerrainSet {
+    pub fn contains(&self, item: &str) -> bool {
+        self.tiles.values().any(|name| name == item)
+    }
+
+    // Returns the masks that don't have a tile yet
+    pub fn missing_masks(&self) -> Vec<u8> {
+        self.kind
+            .masks()
+            .into_iter()
+            .filter(|mask| !self.tiles.contains_key(mask))
+            .collect()
+    }
+
+    // which of pos's neighbours are this terrain. off the grid counts as not
+    pub fn mask_at(&self, grid: &TileGrid<String>, pos: (usize, usize)) -> u8 {
+        let mut mask = 0;
+        for (bit, (dx, dy)) in NEIGHBOUR_OFFSETS {
+            let (x, y) = (pos.0 as i64 + dx, pos.1 as i64 + dy);
+            if x < 0 || y < 0 || x >= grid.cols as i64 || y >= grid.rows as i64 { continue; }
+
+            if grid.get((x as usize, y as usize)).as_deref().is_some_and(|item| self.contains(item)) {
+                mask |= bit;
+            }
+        }
+
+        match self.kind {
+            TerrainKind::Edges4 => mask & (NORTH | EAST | SOUTH | WEST),
+            TerrainKind::Blob47 => blob_mask(mask),
+        }
+    }
+
+    // the icon pos should have, None if the set is missing that one
+    pub fn resolve(&self, grid: &TileGrid<String>, pos: (usize, usize)) -> Option<&String> {
+        self.tiles.get(&self.mask_at(grid, pos))
+    }
+
+    // places (or erases) terrain at pos, then fixes up it and every neighbour
+    // that's the same terrain. anything else is left alone
+    pub fn paint(&self, grid: &mut TileGrid<String>, pos: (usize, usize), place: bool) {
+        if place {
+            // anything in the set will do for now, it gets resolved below
+            let Some(any_tile) = self.tiles.values().next() else { return; };
+            if !grid.get(pos).as_deref().is_some_and(|item| self.contains(item)) {
+                grid.set(pos, Some(any_tile.clone()));
+            }
+        } else if grid.get(pos).as_deref().is_some_and(|item| self.contains(item)) {
+            grid.set(pos, None);
+        }
+
+        let mut affected = grid.neighbors(pos, Connectivity::Eight);
+        affected.push(pos);
+
+        for cell in affected {
+            if !grid.get(cell).as_deref().is_some_and(|item| self.contains(item)) { continue; }
+
+            // missing tiles keep whatever was there
+            if let Some(tile) = self.resolve(grid, cell).cloned() {
+                if grid.get(cell).as_ref() != Some(&tile) { grid.set(cell, Some(tile)); }
+            }
+        }
+    }
+}
+
+impl ToAndFromJsonValue for TerrainSet {
+    fn to_json(&self) -> JsonValue {
+        let mut json_object = object! {
+            name: self.name.clone(),
+            kind: self.kind.name(),
+            tiles: {},
+        };
+
+        for (mask, tile) in self.tiles.iter() {
+            json_object["tiles"][mask.to_string()] = tile.clone().into();
+        }
+
+        return json_object;
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        let kind = match source["kind"].as_str()? {
+            "edges4" => TerrainKind::Edges4,
+            "blob47" => TerrainKind::Blob47,
+            _ => return None,
+        };
+
+        let mut tiles = BTreeMap::new();
+        for (mask, tile) in source["tiles"].entries() {
+            tiles.insert(mask.parse().ok()?, tile.as_str()?.to_owned());
+        }
+
+        Some(TerrainSet { name: source["name"].as_str()?.to_owned(), kind, tiles })
+    }
+}
+
+// tiles are written as file names in the folder, the same way the palette manifest does it,
+// and come back named the way the icons are loaded
+pub fn load_terrain_sets(dir: &Path) -> Vec<TerrainSet> {
+    let Ok(mut input) = fs::File::open(dir.join(TERRAIN_SETS_FILE)) else {
+        return vec![];
+    };
+
+    let mut buffer = String::new();
+    input.read_to_string(&mut buffer).expect("Read to buffer");
+    let source = json::parse(&buffer).expect("Terrain sets are valid json");
+
+    assert_eq!(source["version"], "1.0");
+
+    source["terrains"]
+        .members()
+        .map(|terrain_json| {
+            let mut terrain = TerrainSet::from_json(terrain_json).expect("Valid terrain set");
+            for tile in terrain.tiles.values_mut() {
+                *tile = dir.join(&tile).to_str().expect("Valid path").to_string();
+            }
+            terrain
+        })
+        .collect()
+}
+
+// every tile in the sets should be in dir
+pub fn save_terrain_sets(dir: &Path, terrains: &[&TerrainSet]) {
+    let mut json_object = object! {
+        version: "1.0",
+        terrains: [],
+    };
+
+    for terrain in terrains {
+        let mut terrain = (*terrain).clone();
+        for tile in terrain.tiles.values_mut() {
+            let file_name = Path::new(tile).file_name().expect("Tile has a file name");
+            *tile = file_name.to_str().expect("Valid path").to_string();
+        }
+        json_object["terrains"].push(terrain.to_json()).expect("terrains is an array");
+    }
+
+    let mut output = fs::File::create(dir.join(TERRAIN_SETS_FILE)).expect("File was created");
+    write!(output, "{}", json_object.pretty(4)).expect("Write to file");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIDES: u8 = NORTH | EAST | SOUTH | WEST;
+
+    // a blob set with one tile per mask, named after the mask
+    fn blob_set() -> TerrainSet {
+        let tiles = TerrainKind::Blob47.masks().into_iter().map(|mask| (mask, format!("blob{mask}"))).collect();
+        TerrainSet { name: "blob".to_owned(), kind: TerrainKind::Blob47, tiles }
+    }
+
+    #[test]
+    fn mask_counts() {
+        assert_eq!(TerrainKind::Edges4.masks().len(), 16);
+        assert_eq!(TerrainKind::Blob47.masks().len(), 47);
+    }
+
+    #[test]
+    fn blob_corners_need_both_sides() {
+        // on its own, or with only one side next to it, a corner doesn't count
+        assert_eq!(blob_mask(NORTH_EAST), 0);
+        assert_eq!(blob_mask(NORTH_EAST | NORTH), NORTH);
+        assert_eq!(blob_mask(NORTH_EAST | EAST), EAST);
+        assert_eq!(blob_mask(NORTH_EAST | NORTH | EAST), NORTH_EAST | NORTH | EAST);
+
+        // all the corners with no sides is the same as nothing
+        assert_eq!(blob_mask(NORTH_EAST | SOUTH_EAST | SOUTH_WEST | NORTH_WEST), 0);
+        assert_eq!(blob_mask(SIDES), SIDES);
+        assert_eq!(blob_mask(255), 255);
+        // every corner but one
+        assert_eq!(blob_mask(!SOUTH_WEST), !SOUTH_WEST);
+    }
+
+    #[test]
+    fn blob_masks_are_already_reduced() {
+        for mask in TerrainKind::Blob47.masks() {
+            assert_eq!(blob_mask(mask), mask);
+        }
+    }
+
+    #[test]
+    fn painting_fixes_up_the_neighbours() {
+        let set = blob_set();
+        let mut grid: TileGrid<String> = TileGrid::new(3, 3);
+        for y in 0..3 {
+            for x in 0..3 {
+                set.paint(&mut grid, (x, y), true);
+            }
+        }
+
+        // the middle is surrounded, the corners only see their two sides and the corner between
+        assert_eq!(grid.get((1, 1)).as_deref(), Some("blob255"));
+        assert_eq!(grid.get((0, 0)).as_deref(), Some(format!("blob{}", EAST | SOUTH_EAST | SOUTH).as_str()));
+        assert_eq!(grid.get((1, 0)).as_deref(), Some(format!("blob{}", EAST | SOUTH_EAST | SOUTH | SOUTH_WEST | WEST).as_str()));
+
+        // taking the middle out opens it up for everything around it
+        set.paint(&mut grid, (1, 1), false);
+        assert_eq!(grid.get((1, 1)), &None);
+        assert_eq!(grid.get((1, 0)).as_deref(), Some(format!("blob{}", EAST | WEST).as_str()));
+    }
+}
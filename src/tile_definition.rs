@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use json::{object, JsonValue};
+
+use crate::tile_grid::ToAndFromJsonValue;
+
+// lives next to the icons it describes
+pub const PALETTE_MANIFEST_FILE: &str = "palette.json";
+
+// a game reads these straight out of the map, so the type has to survive
+// the trip. every property is written as {"type": ..., "value": ...}
+#[derive(Debug, Clone, PartialEq)]
+pub enum TileProperty {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TileDefinition {
+    pub description: String,
+    pub tags: Vec<String>,
+    // sorted, so the files don't shuffle around every save
+    pub properties: BTreeMap<String, TileProperty>,
+}
+
+impl ToAndFromJsonValue for TileProperty {
+    fn to_json(&self) -> JsonValue {
+        match self {
+            TileProperty::Bool(value) => object! { "type": "bool", value: *value },
+            TileProperty::Int(value)  => object! { "type": "int",  value: *value },
+            // the type says its a float, so a 1 that comes back is still 1.0
+            TileProperty::Float(value) => object! { "type": "float", value: *value },
+            TileProperty::Text(value)  => object! { "type": "text",  value: value.clone() },
+        }
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        let value = &source["value"];
+        match source["type"].as_str()? {
+            "bool"  => value.as_bool().map(TileProperty::Bool),
+            "int"   => value.as_i64().map(TileProperty::Int),
+            "float" => value.as_f64().map(TileProperty::Float),
+            "text"  => value.as_str().map(|text| TileProperty::Text(text.to_owned())),
+            _ => None,
+        }
+    }
+}
+
+impl ToAndFromJsonValue for TileDefinition {
+    fn to_json(&self) -> JsonValue {
+        let mut json_object = object! {
+            description: self.description.clone(),
+            tags: self.tags.clone(),
+            properties: {},
+        };
+
+        for (name, property) in self.properties.iter() {
+            json_object["properties"][name.as_str()] = property.to_json();
+        }
+
+        return json_object;
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        let mut definition = TileDefinition {
+            description: source["description"].as_str().unwrap_or_default().to_owned(),
+            ..TileDefinition::default()
+        };
+
+        for tag in source["tags"].members() {
+            definition.tags.push(tag.as_str()?.to_owned());
+        }
+        for (name, property) in source["properties"].entries() {
+            definition.properties.insert(name.to_owned(), TileProperty::from_json(property)?);
+        }
+
+        return Some(definition);
+    }
+}
+
+impl std::fmt::Display for TileProperty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TileProperty::Bool(value)  => write!(f, "{value}"),
+            TileProperty::Int(value)   => write!(f, "{value}"),
+            TileProperty::Float(value) => write!(f, "{value:?}"),
+            TileProperty::Text(value)  => write!(f, "\"{value}\""),
+        }
+    }
+}
+
+// the manifest is keyed by file name, so the folder can be moved around.
+// Returns (icon name, definition), named the same way the icons are loaded.
+// a bad manifest (or a bad tile in one) gets reported and left out, the icons still load
+pub fn load_palette_manifest(dir: &Path) -> (Vec<(String, TileDefinition)>, Vec<String>) {
+    let path = dir.join(PALETTE_MANIFEST_FILE);
+    let Ok(mut input) = fs::File::open(&path) else {
+        return (vec![], vec![]);
+    };
+
+    let mut buffer = String::new();
+    if input.read_to_string(&mut buffer).is_err() {
+        return (vec![], vec![format!("{}: can't read it", path.display())]);
+    }
+    let source = match json::parse(&buffer) {
+        Ok(source) => source,
+        Err(error) => return (vec![], vec![format!("{}: {error}", path.display())]),
+    };
+
+    if source["version"] != "1.0" {
+        return (vec![], vec![format!("{}: unknown version {}", path.display(), source["version"])]);
+    }
+
+    let mut definitions = vec![];
+    let mut errors = vec![];
+    for (file_name, value) in source["tiles"].entries() {
+        let name = dir.join(file_name).to_str().expect("Valid path").to_string();
+        match TileDefinition::from_json(value) {
+            Some(definition) => definitions.push((name, definition)),
+            None => errors.push(format!("{}: bad definition for {file_name}", path.display())),
+        }
+    }
+
+    return (definitions, errors);
+}
+
+// definitions are (file name, definition) for the icons in dir
+pub fn save_palette_manifest(dir: &Path, definitions: &[(String, TileDefinition)]) {
+    let mut json_object = object! {
+        version: "1.0",
+        tiles: {},
+    };
+
+    for (file_name, definition) in definitions {
+        json_object["tiles"][file_name.as_str()] = definition.to_json();
+    }
+
+    let mut output = fs::File::create(dir.join(PALETTE_MANIFEST_FILE)).expect("File was created");
+    write!(output, "{}", json_object.pretty(4)).expect("Write to file");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floats_are_numbers_and_stay_floats() {
+        for value in [0.1, 2.0, -1.5e-7, 1234567.125] {
+            let json = TileProperty::Float(value).to_json();
+            assert!(json["value"].is_number());
+            assert_eq!(TileProperty::from_json(&json::parse(&json.dump()).unwrap()), Some(TileProperty::Float(value)));
+        }
+        assert_eq!(TileProperty::Float(0.1).to_json().dump(), r#"{"type":"float","value":0.1}"#);
+    }
+
+    #[test]
+    fn bad_manifests_get_reported_not_loaded() {
+        let dir = std::env::temp_dir().join(format!("tile_editor_palette_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(load_palette_manifest(&dir), (vec![], vec![]));
+
+        fs::write(dir.join(PALETTE_MANIFEST_FILE), "{ oops").unwrap();
+        let (definitions, errors) = load_palette_manifest(&dir);
+        assert!(definitions.is_empty());
+        assert_eq!(errors.len(), 1);
+
+        // one bad tile doesn't take the good ones with it
+        fs::write(dir.join(PALETTE_MANIFEST_FILE), r#"{ "version": "1.0", "tiles": {
+            "good.png": { "description": "fine", "tags": [], "properties": { "height": { "type": "float", "value": 1.5 } } },
+            "bad.png":  { "description": "nope", "tags": [], "properties": { "height": { "type": "float", "value": true } } }
+        } }"#).unwrap();
+        let (definitions, errors) = load_palette_manifest(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(definitions.len(), 1);
+        assert!(definitions[0].0.ends_with("good.png"));
+        assert_eq!(definitions[0].1.properties["height"], TileProperty::Float(1.5));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("bad.png"));
+    }
+}
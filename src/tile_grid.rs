@@ -1,16 +1,229 @@
-use std::cmp::min;
+use std::collections::HashSet;
 
 use json::{object, JsonValue};
 
-#[derive(Debug, Clone, Copy)]
+use crate::topology::HexLayout;
+
+// which cells count as touching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    Four,  // up, down, left, right
+    Eight, // and the diagonals
+    Hex(HexLayout), // the six around it, see Topology::connectivity
+}
+
+// which part of the grid stays put when it's resized.
+// the editor only has hotkeys for the corners so far
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,    Top,    TopRight,
+    Left,       Center, Right,
+    BottomLeft, Bottom, BottomRight,
+}
+
+// how many cells get added (or taken away, if negative) on each side
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EdgeDeltas {
+    pub left: i64,
+    pub right: i64,
+    pub top: i64,
+    pub bottom: i64,
+}
+
+impl EdgeDeltas {
+    // the edges that turn old_size into new_size without moving the anchor.
+    // centered odd changes put the extra cell on the right/bottom
+    pub fn anchored(old_size: (usize, usize), new_size: (usize, usize), anchor: Anchor) -> Self {
+        let d_rows = new_size.0 as i64 - old_size.0 as i64;
+        let d_cols = new_size.1 as i64 - old_size.1 as i64;
+
+        let (left, top) = match anchor {
+            Anchor::TopLeft    => (0,          0         ),
+            Anchor::Top        => (d_cols / 2, 0         ),
+            Anchor::TopRight   => (d_cols,     0         ),
+            Anchor::Left       => (0,          d_rows / 2),
+            Anchor::Center     => (d_cols / 2, d_rows / 2),
+            Anchor::Right      => (d_cols,     d_rows / 2),
+            Anchor::BottomLeft => (0,          d_rows    ),
+            Anchor::Bottom     => (d_cols / 2, d_rows    ),
+            Anchor::BottomRight=> (d_cols,     d_rows    ),
+        };
+
+        EdgeDeltas { left, right: d_cols - left, top, bottom: d_rows - top }
+    }
+
+    // Returns (rows, cols) after the edges move
+    pub fn new_size(&self, old_size: (usize, usize)) -> (usize, usize) {
+        let rows = old_size.0 as i64 + self.top + self.bottom;
+        let cols = old_size.1 as i64 + self.left + self.right;
+        assert!(rows >= 0 && cols >= 0, "can't shrink past nothing");
+        return (rows as usize, cols as usize);
+    }
+
+    // where a cell ends up, anything that's part of a cell (notes, links, ect.) should
+    // go through this. Returns None if its edge was cut off
+    pub fn move_pos(&self, pos: (usize, usize), old_size: (usize, usize)) -> Option<(usize, usize)> {
+        let (rows, cols) = self.new_size(old_size);
+        let x = pos.0 as i64 + self.left;
+        let y = pos.1 as i64 + self.top;
+
+        if x < 0 || y < 0 || x >= cols as i64 || y >= rows as i64 { return None; }
+        return Some((x as usize, y as usize));
+    }
+
+    // move_pos for things between cells, (0.5, 0.5) is the middle of the first cell
+    pub fn move_point(&self, point: (f32, f32), old_size: (usize, usize)) -> Option<(f32, f32)> {
+        let (rows, cols) = self.new_size(old_size);
+        let x = point.0 + self.left as f32;
+        let y = point.1 + self.top  as f32;
+
+        // the far edges are still on the grid, points can sit right on them
+        if x < 0.0 || y < 0.0 || x > cols as f32 || y > rows as f32 { return None; }
+        return Some((x, y));
+    }
+}
+
+// rotations are clockwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridTransform {
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipHorizontal, // left and right swap
+    FlipVertical,   // top and bottom swap
+    Transpose,      // x and y swap
+}
+
+impl GridTransform {
+    // Returns (rows, cols) after the transform
+    pub fn new_size(&self, old_size: (usize, usize)) -> (usize, usize) {
+        let (rows, cols) = old_size;
+        match self {
+            GridTransform::Rotate90 | GridTransform::Rotate270 | GridTransform::Transpose => (cols, rows),
+            _ => (rows, cols),
+        }
+    }
+
+    // where a cell ends up, same deal as EdgeDeltas::move_pos
+    pub fn move_pos(&self, pos: (usize, usize), old_size: (usize, usize)) -> (usize, usize) {
+        let (x, y) = pos;
+        let (rows, cols) = old_size;
+        match self {
+            GridTransform::Rotate90       => (rows - 1 - y, x),
+            GridTransform::Rotate180      => (cols - 1 - x, rows - 1 - y),
+            GridTransform::Rotate270      => (y, cols - 1 - x),
+            GridTransform::FlipHorizontal => (cols - 1 - x, y),
+            GridTransform::FlipVertical   => (x, rows - 1 - y),
+            GridTransform::Transpose      => (y, x),
+        }
+    }
+
+    // move_pos for things between cells, the middle of a cell ends up in the middle of where it goes
+    pub fn move_point(&self, point: (f32, f32), old_size: (usize, usize)) -> (f32, f32) {
+        let (x, y) = point;
+        let (rows, cols) = (old_size.0 as f32, old_size.1 as f32);
+        match self {
+            GridTransform::Rotate90       => (rows - y, x),
+            GridTransform::Rotate180      => (cols - x, rows - y),
+            GridTransform::Rotate270      => (y, cols - x),
+            GridTransform::FlipHorizontal => (cols - x, y),
+            GridTransform::FlipVertical   => (x, rows - y),
+            GridTransform::Transpose      => (y, x),
+        }
+    }
+}
+
+// how a placed item is drawn. flipped first, then turned clockwise
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Orientation {
+    pub quarter_turns: u8, // 0 to 3
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl Orientation {
+    pub fn is_default(&self) -> bool {
+        return *self == Orientation::default();
+    }
+
+    pub fn rotated(&self) -> Orientation {
+        Orientation { quarter_turns: (self.quarter_turns + 1) % 4, ..*self }
+    }
+
+    pub fn flipped(&self) -> Orientation {
+        Orientation { flip_x: !self.flip_x, ..*self }
+    }
+
+    // what a tile facing this way faces after the whole grid is transformed.
+    // both flips are the same as turning twice, so it comes out with flip_y off
+    pub fn transformed(&self, transform: GridTransform) -> Orientation {
+        let (mut turns, mut flip_x) = (self.quarter_turns, self.flip_x);
+        if self.flip_y { turns += 2; flip_x = !flip_x; }
+
+        let turns = match transform {
+            GridTransform::Rotate90  => turns + 1,
+            GridTransform::Rotate180 => turns + 2,
+            GridTransform::Rotate270 => turns + 3,
+            // mirroring makes the turns go the other way
+            GridTransform::FlipHorizontal => { flip_x = !flip_x; 4 - turns % 4 }
+            GridTransform::FlipVertical   => { flip_x = !flip_x; 6 - turns % 4 }
+            GridTransform::Transpose      => { flip_x = !flip_x; 7 - turns % 4 }
+        };
+
+        Orientation { quarter_turns: turns % 4, flip_x, flip_y: false }
+    }
+}
+
+impl ToAndFromJsonValue for Orientation {
+    fn to_json(&self) -> JsonValue {
+        object! {
+            rotation: self.quarter_turns as u32 * 90,
+            flip_x: self.flip_x,
+            flip_y: self.flip_y,
+        }
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        let quarter_turns = match source["rotation"].as_u32().unwrap_or(0) {
+            0   => 0,
+            90  => 1,
+            180 => 2,
+            270 => 3,
+            _ => return None,
+        };
+
+        Some(Orientation {
+            quarter_turns,
+            flip_x: source["flip_x"].as_bool().unwrap_or(false),
+            flip_y: source["flip_y"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Tile<T> {
     item: Option<T>,
+    // free form text, stays on the cell no matter what item is placed
+    note: Option<String>,
+    // goes with the item, placing a new one resets it
+    orientation: Orientation,
+}
+
+// can't derive it, that would make T need a default too
+impl<T> Default for Tile<T> {
+    fn default() -> Self {
+        Tile { item: None, note: None, orientation: Orientation::default() }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TileGrid<T> {
     pub rows: usize,
     pub cols: usize,
+    // where cell (0,0) sits compared to where it was when the grid was made. resizing from
+    // the left or top moves it, so a merge can line up cells that got shifted over
+    pub origin: (i64, i64),
     tiles: Vec<Tile<T>>,
 }
 
@@ -19,11 +232,12 @@ impl<T> TileGrid<T> {
         let mut result = TileGrid {
             rows,
             cols,
+            origin: (0, 0),
             tiles: Vec::with_capacity(rows * cols),
         };
 
         for _ in 0..rows * cols {
-            result.tiles.push(Tile { item: None });
+            result.tiles.push(Tile::default());
         }
 
         return result;
@@ -40,32 +254,186 @@ impl<T> TileGrid<T> {
     }
 
     pub fn set(&mut self, pos: (usize, usize), current: Option<T>) {
+        self.set_oriented(pos, current, Orientation::default());
+    }
+
+    pub fn set_oriented(&mut self, pos: (usize, usize), current: Option<T>, orientation: Orientation) {
         let index = pos_to_index(pos, self.size());
         self.tiles[index].item = current;
+        self.tiles[index].orientation = orientation;
+    }
+
+    pub fn get_orientation(&self, pos: (usize, usize)) -> Orientation {
+        return self.tiles[pos_to_index(pos, self.size())].orientation;
+    }
+
+    // the whole cell, item and note, for moving cells around as one thing
+    pub fn get_tile(&self, pos: (usize, usize)) -> &Tile<T> {
+        return &self.tiles[pos_to_index(pos, self.size())];
+    }
+
+    pub fn set_tile(&mut self, pos: (usize, usize), tile: Tile<T>) {
+        let index = pos_to_index(pos, self.size());
+        self.tiles[index] = tile;
+    }
+
+    // every placed item, in index order
+    pub fn items(&self) -> impl Iterator<Item = &T> {
+        self.tiles.iter().filter_map(|tile| tile.item.as_ref())
+    }
+
+    pub fn get_note(&self, pos: (usize, usize)) -> Option<&str> {
+        return self.tiles[pos_to_index(pos, self.size())].note.as_deref();
+    }
+
+    // empty notes get removed
+    pub fn set_note(&mut self, pos: (usize, usize), note: Option<String>) {
+        let index = pos_to_index(pos, self.size());
+        self.tiles[index].note = note.filter(|text| !text.is_empty());
+    }
+
+    // empties every cell in the box, item and note. corners are inclusive
+    pub fn clear_area(&mut self, start: (usize, usize), end: (usize, usize)) {
+        for y in start.1.min(end.1)..=start.1.max(end.1) {
+            for x in start.0.min(end.0)..=start.0.max(end.0) {
+                let index = pos_to_index((x, y), self.size());
+                self.tiles[index] = Tile::default();
+            }
+        }
     }
 
     // Returns (rows, cols)
     pub fn size(&self) -> (usize, usize) {
         return (self.rows, self.cols);
     }
+
+    // the cells touching pos that are inside the grid
+    pub fn neighbors(&self, pos: (usize, usize), connectivity: Connectivity) -> Vec<(usize, usize)> {
+        const FOUR : [(i64, i64); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+        const EIGHT: [(i64, i64); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+        let cells: Vec<(i64, i64)> = match connectivity {
+            Connectivity::Four  => FOUR .iter().map(|(dx, dy)| (pos.0 as i64 + dx, pos.1 as i64 + dy)).collect(),
+            Connectivity::Eight => EIGHT.iter().map(|(dx, dy)| (pos.0 as i64 + dx, pos.1 as i64 + dy)).collect(),
+            Connectivity::Hex(layout) => layout.neighbors((pos.0 as i64, pos.1 as i64)).to_vec(),
+        };
+
+        cells
+            .into_iter()
+            .filter(|(x, y)| 0 <= *x && *x < self.cols as i64 && 0 <= *y && *y < self.rows as i64)
+            .map(|(x, y)| (x as usize, y as usize))
+            .collect()
+    }
 }
 impl<T> TileGrid<T>
 where
     T: Clone,
 {
-    pub fn resize(&mut self, new_rows: usize, new_cols: usize) {
+    // for a plain resize, EdgeDeltas::anchored with Anchor::TopLeft
+    pub fn resize_edges(&mut self, edges: EdgeDeltas) {
+        let (new_rows, new_cols) = edges.new_size(self.size());
+        let mut new_grid = Self::new(new_rows, new_cols);
+        new_grid.origin = (self.origin.0 - edges.left, self.origin.1 - edges.top);
+
+        // move the whole tile, so notes come along
+        for (index, tile) in self.tiles.iter().enumerate() {
+            let Some(new_pos) = edges.move_pos(index_to_pos(index, self.size()), self.size()) else { continue; };
+            new_grid.set_tile(new_pos, tile.clone());
+        }
+
+        *self = new_grid;
+    }
+
+    // notes go with their tiles, and the tiles turn with the grid
+    pub fn transformed(&self, transform: GridTransform) -> TileGrid<T> {
+        let (new_rows, new_cols) = transform.new_size(self.size());
         let mut new_grid = Self::new(new_rows, new_cols);
 
-        for x in 0..min(self.cols, new_grid.cols) {
-            for y in 0..min(self.rows, new_grid.rows) {
-                let tile = self.get((x, y));
-                if tile.is_some() {
-                    new_grid.set((x, y), tile.clone())
+        for (index, tile) in self.tiles.iter().enumerate() {
+            let new_pos = transform.move_pos(index_to_pos(index, self.size()), self.size());
+            let orientation = tile.orientation.transformed(transform);
+            new_grid.set_tile(new_pos, Tile { orientation, ..tile.clone() });
+        }
+
+        return new_grid;
+    }
+
+    // a copy of the box between the corners, inclusive, notes and all
+    pub fn extract(&self, start: (usize, usize), end: (usize, usize)) -> TileGrid<T> {
+        let (min_x, min_y) = (start.0.min(end.0), start.1.min(end.1));
+        let (max_x, max_y) = (start.0.max(end.0), start.1.max(end.1));
+
+        let mut result = Self::new(max_y - min_y + 1, max_x - min_x + 1);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                result.set_tile((x - min_x, y - min_y), self.get_tile((x, y)).clone());
+            }
+        }
+        return result;
+    }
+
+    // writes source over this grid with its top left corner at origin,
+    // empty cells in source erase. whatever hangs off the edge is dropped
+    pub fn paste(&mut self, origin: (usize, usize), source: &TileGrid<T>) {
+        for y in 0..source.rows {
+            for x in 0..source.cols {
+                let pos = (origin.0 + x, origin.1 + y);
+                if pos.0 >= self.cols || pos.1 >= self.rows { continue; }
+
+                self.set_tile(pos, source.get_tile((x, y)).clone());
+            }
+        }
+    }
+}
+
+impl<T> TileGrid<T>
+where
+    T: Clone + PartialEq,
+{
+    // replaces every cell connected to start that holds the same thing start does,
+    // empty counts as a thing. uses its own stack, big grids won't blow ours.
+    // Returns the number of cells changed
+    pub fn flood_fill(&mut self, start: (usize, usize), current: Option<T>, orientation: Orientation, connectivity: Connectivity) -> usize {
+        let target = self.get(start).clone();
+        if target == current { return 0; }
+
+        let mut stack = vec![start];
+        let mut seen = HashSet::from([start]);
+        let mut changed = 0;
+
+        while let Some(pos) = stack.pop() {
+            self.set_oriented(pos, current.clone(), orientation);
+            changed += 1;
+
+            for next in self.neighbors(pos, connectivity) {
+                if *self.get(next) == target && seen.insert(next) {
+                    stack.push(next);
                 }
             }
         }
 
-        *self = new_grid;
+        return changed;
+    }
+
+    // swaps the item in every cell holding find, inside the box if there is one.
+    // notes and orientations stay put. Returns the number of cells changed
+    pub fn replace_all(&mut self, find: &T, replace: &T, area: Option<((usize, usize), (usize, usize))>) -> usize {
+        if find == replace { return 0; }
+
+        let (start, end) = area.unwrap_or(((0, 0), (self.cols - 1, self.rows - 1)));
+        let mut changed = 0;
+
+        for y in start.1.min(end.1)..=start.1.max(end.1) {
+            for x in start.0.min(end.0)..=start.0.max(end.0) {
+                let index = pos_to_index((x, y), self.size());
+                if self.tiles[index].item.as_ref() == Some(find) {
+                    self.tiles[index].item = Some(replace.clone());
+                    changed += 1;
+                }
+            }
+        }
+
+        return changed;
     }
 }
 
@@ -90,6 +458,12 @@ pub fn index_to_pos(index: usize, size: (usize, usize)) -> (usize, usize) {
     return pos;
 }
 
+// "(x,y)" -> (x, y), the way tiles are keyed in the json
+pub fn parse_pos_key<N: std::str::FromStr>(key: &str) -> Option<(N, N)> {
+    let (x, y) = key.strip_prefix("(")?.strip_suffix(")")?.split_once(",")?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
 pub trait ToAndFromJsonValue
 where
     Self: Sized,
@@ -108,8 +482,15 @@ where
             "rows": self.rows,
             "cols": self.cols,
             tiles: {},
+            notes: {},
+            // older readers don't know about this one, they just get everything facing up
+            orientations: {},
             // list: [],
         };
+        // only grids that were resized from the left or top have one
+        if self.origin != (0, 0) {
+            json_object["origin"] = object! { x: self.origin.0, y: self.origin.1 };
+        }
         // why don't i put multiple different representations in here?
         // make it parse how you want?
         // Seems too theoretical. who would actually do that?
@@ -126,28 +507,52 @@ where
                     let i_by_j = format!("({i},{j})");
                     json_object["tiles"][i_by_j] = to_push;
                 }
+
+                if let Some(note) = self.get_note((i, j)) {
+                    json_object["notes"][format!("({i},{j})")] = note.into();
+                }
+
+                let orientation = self.get_orientation((i, j));
+                if self.get((i, j)).is_some() && !orientation.is_default() {
+                    json_object["orientations"][format!("({i},{j})")] = orientation.to_json();
+                }
             }
         }
 
         return json_object;
     }
 
+    // None for anything that isn't a good grid, it might have come off the clipboard
     fn from_json(source: &JsonValue) -> Option<Self> {
-        assert_eq!(source["version"], "1.0");
+        if source["version"] != "1.0" { return None; }
+
+        let (rows, cols) = (source["rows"].as_usize()?, source["cols"].as_usize()?);
+        let mut new_grid: TileGrid<T> = TileGrid::new(rows, cols);
+        if !source["origin"].is_null() {
+            new_grid.origin = (source["origin"]["x"].as_i64()?, source["origin"]["y"].as_i64()?);
+        }
+        let in_grid = |key: &str| -> Option<(usize, usize)> {
+            let pos: (usize, usize) = parse_pos_key(key)?;
+            if pos.0 >= cols || pos.1 >= rows { return None; }
+            Some(pos)
+        };
 
-        let mut new_grid: TileGrid<T> = TileGrid::new(
-            source["rows"].as_usize().expect("rows exists"),
-            source["cols"].as_usize().expect("cols exists"),
-        );
+        for (key, value) in source["tiles"].entries() {
+            let pos = in_grid(key)?;
+            new_grid.set(pos, Some(T::from_json(value)?));
+        }
 
-        source["tiles"].entries().for_each(|(key, value)| {
-            let pos = key[1..key.len() - 1]
-                .split_once(",")
-                .map(|(x, y)| (x.parse().unwrap(), y.parse().unwrap()))
-                .expect("Parse index's correctly");
+        // older saves don't have notes, thats fine
+        for (key, value) in source["notes"].entries() {
+            let pos = in_grid(key)?;
+            new_grid.set_note(pos, Some(value.as_str()?.to_owned()));
+        }
 
-            new_grid.set(pos, Some(T::from_json(value).expect("Valid Value")));
-        });
+        for (key, value) in source["orientations"].entries() {
+            let pos = in_grid(key)?;
+            let index = pos_to_index(pos, new_grid.size());
+            new_grid.tiles[index].orientation = Orientation::from_json(value)?;
+        }
 
         // TODO? reuse as list of rows?
         // source["list"].members().enumerate().for_each(|(i, val)| {
@@ -157,3 +562,216 @@ where
         return Some(new_grid);
     }
 }
+
+// for writing grids out in tests, one letter per cell and spaces are empty
+#[cfg(test)]
+impl TileGrid<String> {
+    pub fn from_rows(rows: &[&str]) -> Self {
+        let mut grid = TileGrid::new(rows.len(), rows.first().map_or(0, |row| row.chars().count()));
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c != ' ' { grid.set((x, y), Some(c.to_string())); }
+            }
+        }
+        return grid;
+    }
+
+    // only the first letter of each item, so it's for grids made with from_rows
+    pub fn to_rows(&self) -> Vec<String> {
+        (0..self.rows)
+            .map(|y| (0..self.cols).map(|x| self.get((x, y)).as_deref().and_then(|item| item.chars().next()).unwrap_or(' ')).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_items_notes_and_orientations() {
+        let mut grid: TileGrid<String> = TileGrid::new(2, 3);
+        grid.set_oriented((2, 1), Some("wall".to_owned()), Orientation::default().rotated());
+        grid.set_note((0, 0), Some("spawn here".to_owned()));
+
+        let loaded: TileGrid<String> = TileGrid::from_json(&grid.to_json()).unwrap();
+        assert_eq!(loaded.size(), (2, 3));
+        assert_eq!(loaded.get_tile((2, 1)), grid.get_tile((2, 1)));
+        assert_eq!(loaded.get_note((0, 0)), Some("spawn here"));
+    }
+
+    #[test]
+    fn bad_grids_are_none_instead_of_panicking() {
+        let bad = [
+            r#"{ "hello": "world" }"#,
+            r#"{ "version": "2.0", "rows": 1, "cols": 1 }"#,
+            r#"{ "version": "1.0", "rows": 1 }"#,
+            r#"{ "version": "1.0", "rows": 1, "cols": 1, "tiles": { "nonsense": "a" } }"#,
+            r#"{ "version": "1.0", "rows": 1, "cols": 1, "tiles": { "(5,0)": "a" } }"#,
+            r#"{ "version": "1.0", "rows": 1, "cols": 1, "tiles": { "(0,0)": 7 } }"#,
+            r#"{ "version": "1.0", "rows": 1, "cols": 1, "notes": { "(0,0)": false } }"#,
+            r#"{ "version": "1.0", "rows": 1, "cols": 1, "orientations": { "(0,0)": { "rotation": 45 } } }"#,
+        ];
+        for source in bad {
+            assert!(TileGrid::<String>::from_json(&json::parse(source).unwrap()).is_none(), "{source}");
+        }
+    }
+
+    #[test]
+    fn anchored_edges_keep_the_anchor_still() {
+        let grow = |anchor| EdgeDeltas::anchored((2, 3), (4, 5), anchor);
+        assert_eq!(grow(Anchor::TopLeft),     EdgeDeltas { left: 0, right: 2, top: 0, bottom: 2 });
+        assert_eq!(grow(Anchor::Right),       EdgeDeltas { left: 2, right: 0, top: 1, bottom: 1 });
+        assert_eq!(grow(Anchor::BottomRight), EdgeDeltas { left: 2, right: 0, top: 2, bottom: 0 });
+
+        // the odd cell goes on the right/bottom, growing or shrinking
+        assert_eq!(EdgeDeltas::anchored((2, 2), (5, 5), Anchor::Center), EdgeDeltas { left: 1, right: 2, top: 1, bottom: 2 });
+        assert_eq!(EdgeDeltas::anchored((5, 5), (2, 2), Anchor::Center), EdgeDeltas { left: -1, right: -2, top: -1, bottom: -2 });
+
+        for anchor in [Anchor::TopLeft, Anchor::Top, Anchor::Left, Anchor::Center, Anchor::BottomRight] {
+            assert_eq!(EdgeDeltas::anchored((3, 4), (6, 2), anchor).new_size((3, 4)), (6, 2));
+        }
+    }
+
+    #[test]
+    fn growing_from_the_bottom_right_pushes_cells_over() {
+        let mut grid = TileGrid::from_rows(&[
+            "ab",
+            "cd",
+        ]);
+        grid.set_note((1, 1), Some("d's note".to_owned()));
+        grid.resize_edges(EdgeDeltas::anchored((2, 2), (3, 4), Anchor::BottomRight));
+
+        assert_eq!(grid.to_rows(), vec!["    ", "  ab", "  cd"]);
+        assert_eq!(grid.get_note((3, 2)), Some("d's note"));
+        assert_eq!(grid.origin, (-2, -1));
+    }
+
+    #[test]
+    fn shrinking_cuts_off_the_far_side_from_the_anchor() {
+        let rows = ["abc", "def", "ghi"];
+
+        let mut top_left = TileGrid::from_rows(&rows);
+        top_left.resize_edges(EdgeDeltas::anchored((3, 3), (2, 2), Anchor::TopLeft));
+        assert_eq!(top_left.to_rows(), vec!["ab", "de"]);
+        assert_eq!(top_left.origin, (0, 0));
+
+        let mut bottom_right = TileGrid::from_rows(&rows);
+        bottom_right.resize_edges(EdgeDeltas::anchored((3, 3), (2, 2), Anchor::BottomRight));
+        assert_eq!(bottom_right.to_rows(), vec!["ef", "hi"]);
+        assert_eq!(bottom_right.origin, (1, 1));
+
+        let mut left = TileGrid::from_rows(&rows);
+        left.resize_edges(EdgeDeltas::anchored((3, 3), (1, 2), Anchor::Left));
+        assert_eq!(left.to_rows(), vec!["de"]);
+    }
+
+    #[test]
+    fn moved_positions_are_none_once_cut_off() {
+        let edges = EdgeDeltas { left: -1, right: 0, top: 2, bottom: -1 };
+        let old_size = (3, 3);
+        assert_eq!(edges.new_size(old_size), (4, 2));
+
+        assert_eq!(edges.move_pos((0, 0), old_size), None);
+        assert_eq!(edges.move_pos((1, 0), old_size), Some((0, 2)));
+        assert_eq!(edges.move_pos((2, 1), old_size), Some((1, 3)));
+        assert_eq!(edges.move_pos((2, 2), old_size), None);
+
+        // points can sit on the far edge, but not past it
+        assert_eq!(edges.move_point((3.0, 2.0), old_size), Some((2.0, 4.0)));
+        assert_eq!(edges.move_point((0.5, 0.5), old_size), None);
+    }
+
+    const TRANSFORMS: [GridTransform; 6] = [
+        GridTransform::Rotate90, GridTransform::Rotate180, GridTransform::Rotate270,
+        GridTransform::FlipHorizontal, GridTransform::FlipVertical, GridTransform::Transpose,
+    ];
+
+    // every way a tile can face, after transformed drops flip_y
+    fn all_orientations() -> Vec<Orientation> {
+        (0..4)
+            .flat_map(|quarter_turns| [false, true].map(|flip_x| Orientation { quarter_turns, flip_x, flip_y: false }))
+            .collect()
+    }
+
+    #[test]
+    fn transforms_move_cells_on_a_non_square_grid() {
+        let grid = TileGrid::from_rows(&[
+            "abc",
+            "def",
+        ]);
+        let after = |transform| grid.transformed(transform).to_rows();
+
+        assert_eq!(after(GridTransform::Rotate90),       vec!["da", "eb", "fc"]);
+        assert_eq!(after(GridTransform::Rotate180),      vec!["fed", "cba"]);
+        assert_eq!(after(GridTransform::Rotate270),      vec!["cf", "be", "ad"]);
+        assert_eq!(after(GridTransform::FlipHorizontal), vec!["cba", "fed"]);
+        assert_eq!(after(GridTransform::FlipVertical),   vec!["def", "abc"]);
+        assert_eq!(after(GridTransform::Transpose),      vec!["ad", "be", "cf"]);
+    }
+
+    #[test]
+    fn move_point_agrees_with_move_pos() {
+        let old_size = (2, 3);
+        for transform in TRANSFORMS {
+            for y in 0..2 {
+                for x in 0..3 {
+                    let (new_x, new_y) = transform.move_pos((x, y), old_size);
+                    let middle = transform.move_point((x as f32 + 0.5, y as f32 + 0.5), old_size);
+                    assert_eq!(middle, (new_x as f32 + 0.5, new_y as f32 + 0.5), "{:?}", transform);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn orientations_turn_with_the_grid() {
+        let facing = Orientation { quarter_turns: 1, flip_x: false, flip_y: false };
+        let mut grid: TileGrid<String> = TileGrid::new(2, 3);
+        grid.set_oriented((2, 0), Some("arrow".to_owned()), facing);
+        grid.set_note((2, 0), Some("points down".to_owned()));
+
+        let rotated = grid.transformed(GridTransform::Rotate90);
+        assert_eq!(rotated.get((1, 2)), &Some("arrow".to_owned()));
+        assert_eq!(rotated.get_note((1, 2)), Some("points down"));
+        assert_eq!(rotated.get_orientation((1, 2)), Orientation { quarter_turns: 2, ..facing });
+
+        let flipped = grid.transformed(GridTransform::FlipHorizontal);
+        assert_eq!(flipped.get_orientation((0, 0)), Orientation { quarter_turns: 3, flip_x: true, flip_y: false });
+    }
+
+    #[test]
+    fn transforms_compose_like_the_grid_does() {
+        let grid = TileGrid::from_rows(&["abc", "def"]);
+        let twice = |first: GridTransform, second: GridTransform| grid.transformed(first).transformed(second);
+
+        let pairs = [
+            (GridTransform::Rotate90, GridTransform::Rotate90, GridTransform::Rotate180),
+            (GridTransform::Rotate90, GridTransform::Rotate180, GridTransform::Rotate270),
+            (GridTransform::FlipHorizontal, GridTransform::FlipVertical, GridTransform::Rotate180),
+            (GridTransform::Rotate90, GridTransform::FlipHorizontal, GridTransform::Transpose),
+        ];
+        for (first, second, same_as) in pairs {
+            assert_eq!(twice(first, second).to_rows(), grid.transformed(same_as).to_rows(), "{:?} {:?}", first, second);
+            for orientation in all_orientations() {
+                assert_eq!(
+                    orientation.transformed(first).transformed(second),
+                    orientation.transformed(same_as),
+                    "{:?} {:?} {:?}", orientation, first, second,
+                );
+            }
+        }
+
+        // and doing a flip twice, or turning all the way around, changes nothing
+        for orientation in all_orientations() {
+            let around = (0..4).fold(orientation, |facing, _| facing.transformed(GridTransform::Rotate90));
+            assert_eq!(around, orientation);
+            for flip in [GridTransform::FlipHorizontal, GridTransform::FlipVertical, GridTransform::Transpose] {
+                assert_eq!(orientation.transformed(flip).transformed(flip), orientation, "{:?}", flip);
+            }
+        }
+        // flip_y is the same as turning twice and flipping x
+        let flip_y = Orientation { quarter_turns: 0, flip_x: false, flip_y: true };
+        assert_eq!(flip_y.transformed(GridTransform::Rotate90), Orientation { quarter_turns: 3, flip_x: true, flip_y: false });
+    }
+}
@@ -0,0 +1,263 @@
+use json::{object, JsonValue};
+
+use crate::tile_grid::*;
+use crate::region::*;
+use crate::map_object::*;
+use crate::topology::*;
+use crate::projection::*;
+
+// bottom to top, the order they get drawn in
+pub const DEFAULT_LAYER_NAMES: [&str; 3] = ["ground", "items", "overlay"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileLayer<T> {
+    pub name: String,
+    pub grid: TileGrid<T>,
+}
+
+// a stack of grids that all share the same rows/cols, so you can put
+// a chest on a floor tile without losing the floor
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileMap<T> {
+    pub rows: usize,
+    pub cols: usize,
+    layers: Vec<TileLayer<T>>,
+    active_layer: usize,
+    // drawn in order, so later ones end up on top
+    pub regions: Vec<Region>,
+    // drawn over everything, in order
+    pub objects: Vec<MapObject>,
+    // square or hex, the layers are the same rows and cols either way
+    pub topology: Topology,
+    // just how the editor draws it, so a game knows which way the art faces
+    pub projection: Projection,
+}
+
+impl<T> TileMap<T> {
+    pub fn new(rows: usize, cols: usize, layer_names: &[&str]) -> Self {
+        assert!(!layer_names.is_empty(), "map needs at least one layer");
+
+        let layers = layer_names
+            .iter()
+            .map(|name| TileLayer { name: name.to_string(), grid: TileGrid::new(rows, cols) })
+            .collect();
+
+        TileMap { rows, cols, layers, active_layer: 0, regions: vec![], objects: vec![], topology: Topology::Square, projection: Projection::TopDown }
+    }
+
+    // Returns (rows, cols)
+    pub fn size(&self) -> (usize, usize) {
+        return (self.rows, self.cols);
+    }
+
+    // bottom to top
+    pub fn layers(&self) -> &[TileLayer<T>] {
+        return &self.layers;
+    }
+
+    pub fn active_layer_index(&self) -> usize {
+        return self.active_layer;
+    }
+
+    pub fn layer_grid_mut(&mut self, index: usize) -> &mut TileGrid<T> {
+        return &mut self.layers[index].grid;
+    }
+
+    pub fn active_layer(&self) -> &TileLayer<T> {
+        return &self.layers[self.active_layer];
+    }
+
+    pub fn active_grid(&self) -> &TileGrid<T> {
+        return &self.layers[self.active_layer].grid;
+    }
+
+    pub fn set_active_layer_by_name(&mut self, name: &str) {
+        let index = self.layers.iter().position(|layer| layer.name == name);
+        self.active_layer = index.expect("Layer exists");
+    }
+
+    pub fn cycle_active_layer(&mut self, count: i32) {
+        let len = self.layers.len() as i32;
+        self.active_layer = (((self.active_layer as i32 + count) % len + len) % len) as usize;
+    }
+
+    // Returns the items at pos and how they face, bottom to top, skipping empty layers
+    pub fn get_stack(&self, pos: (usize, usize)) -> Vec<(&T, Orientation)> {
+        self.layers
+            .iter()
+            .filter_map(|layer| Some((layer.grid.get(pos).as_ref()?, layer.grid.get_orientation(pos))))
+            .collect()
+    }
+}
+impl<T> TileMap<T>
+where
+    T: Clone,
+{
+    pub fn resize_edges(&mut self, edges: EdgeDeltas) {
+        for layer in self.layers.iter_mut() {
+            layer.grid.resize_edges(edges);
+        }
+        let old_size = self.size();
+        self.regions = self.regions.iter().filter_map(|region| region.resized(edges, old_size)).collect();
+        self.objects.retain_mut(|map_object| {
+            let Some(position) = edges.move_point(map_object.position, old_size) else { return false; };
+            map_object.position = position;
+            true
+        });
+        if let Topology::Hex(layout) = self.topology {
+            self.topology = Topology::Hex(layout.shifted_by(edges));
+        }
+        (self.rows, self.cols) = edges.new_size(old_size);
+    }
+
+    pub fn transform(&mut self, transform: GridTransform) {
+        for layer in self.layers.iter_mut() {
+            layer.grid = layer.grid.transformed(transform);
+        }
+        let old_size = self.size();
+        for region in self.regions.iter_mut() {
+            *region = region.transformed(transform, old_size);
+        }
+        for map_object in self.objects.iter_mut() {
+            map_object.position = transform.move_point(map_object.position, old_size);
+        }
+        (self.rows, self.cols) = transform.new_size(old_size);
+    }
+}
+
+impl<T> ToAndFromJsonValue for TileMap<T>
+where
+    T: ToAndFromJsonValue,
+{
+    fn to_json(&self) -> JsonValue {
+        let mut json_object = object! {
+            version: "1.0",
+            "rows": self.rows,
+            "cols": self.cols,
+            active_layer: self.active_layer().name.clone(),
+            topology: self.topology.to_json(),
+            projection: self.projection.to_json(),
+            layers: [],
+            regions: [],
+            objects: [],
+        };
+
+        // every layer is a normal grid with a name stuck on it,
+        // so you can pull one out and parse it by itself
+        for layer in self.layers.iter() {
+            let mut layer_json = layer.grid.to_json();
+            layer_json["name"] = layer.name.clone().into();
+            json_object["layers"].push(layer_json).expect("layers is an array");
+        }
+        for region in self.regions.iter() {
+            json_object["regions"].push(region.to_json()).expect("regions is an array");
+        }
+        for map_object in self.objects.iter() {
+            json_object["objects"].push(map_object.to_json()).expect("objects is an array");
+        }
+
+        return json_object;
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        // old saves are a single grid, treat it as the ground layer
+        if source["layers"].is_null() {
+            let grid: TileGrid<T> = TileGrid::from_json(source)?;
+            return Some(TileMap {
+                rows: grid.rows,
+                cols: grid.cols,
+                layers: vec![TileLayer { name: DEFAULT_LAYER_NAMES[0].to_string(), grid }],
+                active_layer: 0,
+                regions: vec![],
+                objects: vec![],
+                topology: Topology::Square,
+                projection: Projection::TopDown,
+            });
+        }
+
+        // a stale or hand edited save is None, not a crash
+        if source["version"] != "1.0" { return None; }
+
+        let rows = source["rows"].as_usize()?;
+        let cols = source["cols"].as_usize()?;
+
+        let layers: Vec<TileLayer<T>> = source["layers"]
+            .members()
+            .map(|layer_json| {
+                let name = layer_json["name"].as_str()?.to_string();
+                let grid: TileGrid<T> = TileGrid::from_json(layer_json)?;
+                if grid.size() != (rows, cols) { return None; }
+                Some(TileLayer { name, grid })
+            })
+            .collect::<Option<_>>()?;
+
+        if layers.is_empty() { return None; }
+
+        // older saves don't have regions or objects
+        let regions = source["regions"]
+            .members()
+            .map(Region::from_json)
+            .collect::<Option<_>>()?;
+
+        let objects = source["objects"]
+            .members()
+            .map(MapObject::from_json)
+            .collect::<Option<_>>()?;
+
+        // older saves are all squares
+        let topology = if source["topology"].is_null() {
+            Topology::Square
+        } else {
+            Topology::from_json(&source["topology"])?
+        };
+
+        let projection = if source["projection"].is_null() {
+            Projection::TopDown
+        } else {
+            Projection::from_json(&source["projection"])?
+        };
+
+        let mut new_map = TileMap { rows, cols, layers, active_layer: 0, regions, objects, topology, projection };
+        if let Some(name) = source["active_layer"].as_str() {
+            new_map.set_active_layer_by_name(name);
+        }
+
+        return Some(new_map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_layers() {
+        let mut map: TileMap<String> = TileMap::new(2, 3, &DEFAULT_LAYER_NAMES);
+        map.layer_grid_mut(1).set((2, 1), Some("chest".to_owned()));
+        map.set_active_layer_by_name(DEFAULT_LAYER_NAMES[1]);
+
+        let loaded = TileMap::from_json(&json::parse(&map.to_json().dump()).unwrap()).unwrap();
+        assert_eq!(loaded, map);
+    }
+
+    #[test]
+    fn broken_saves_dont_load() {
+        let good = TileMap::<String>::new(2, 2, &DEFAULT_LAYER_NAMES).to_json();
+        let broken = |edit: &dyn Fn(&mut JsonValue)| {
+            let mut source = good.clone();
+            edit(&mut source);
+            TileMap::<String>::from_json(&source)
+        };
+
+        assert!(broken(&|_| {}).is_some());
+        assert!(broken(&|source| source["version"] = "0.1".into()).is_none());
+        assert!(broken(&|source| { source.remove("rows"); }).is_none());
+        assert!(broken(&|source| { source["layers"][0].remove("name"); }).is_none());
+        assert!(broken(&|source| source["layers"][1]["cols"] = 5.into()).is_none());
+        assert!(broken(&|source| source["layers"] = JsonValue::new_array()).is_none());
+        assert!(broken(&|source| source["regions"] = json::array![{ name: "room" }]).is_none());
+        assert!(broken(&|source| source["objects"] = json::array![{ name: "chest", icon: "c", x: "1" }]).is_none());
+        assert!(broken(&|source| source["topology"] = json::object!{ kind: "triangle" }).is_none());
+        assert!(broken(&|source| source["projection"] = "oblique".into()).is_none());
+    }
+}
@@ -0,0 +1,194 @@
+// counting what's in a grid, for balancing loot and working out how much art a map needs
+
+use std::collections::BTreeMap;
+
+use json::{object, JsonValue};
+
+use crate::tile_grid::*;
+use crate::icon_server::MyIconServer;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileStats {
+    pub size: (usize, usize),
+    // cells holding each tile, sorted by name
+    pub counts: BTreeMap<String, usize>,
+    pub empty: usize,
+    // (min, max) of every cell with something in it, inclusive. None for an empty grid
+    pub bounds: Option<((usize, usize), (usize, usize))>,
+    // icons in the pallet that the grid doesn't use
+    pub unused: Vec<String>,
+}
+
+pub fn tile_stats<H>(grid: &TileGrid<String>, icon_server: &MyIconServer<H>) -> TileStats {
+    let mut counts = BTreeMap::new();
+    let mut empty = 0;
+    let mut bounds: Option<((usize, usize), (usize, usize))> = None;
+
+    for y in 0..grid.rows {
+        for x in 0..grid.cols {
+            let Some(item) = grid.get((x, y)) else {
+                empty += 1;
+                continue;
+            };
+            *counts.entry(item.clone()).or_insert(0) += 1;
+
+            bounds = Some(match bounds {
+                None => ((x, y), (x, y)),
+                Some((min, max)) => ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+            });
+        }
+    }
+
+    let unused = icon_server.assets
+        .iter()
+        .map(|(name, _)| name)
+        .filter(|name| !counts.contains_key(*name))
+        .cloned()
+        .collect();
+
+    TileStats { size: grid.size(), counts, empty, bounds, unused }
+}
+
+// commas and quotes in a name need the name quoted
+fn csv_field(text: &str) -> String {
+    if !text.contains([',', '"', '\n']) { return text.to_owned(); }
+    return format!("\"{}\"", text.replace('"', "\"\""));
+}
+
+impl TileStats {
+    // what the stats panel shows
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("{} x {} cells, {} empty", self.size.1, self.size.0, self.empty),
+            match self.bounds {
+                Some((min, max)) => format!("content from ({},{}) to ({},{})", min.0, min.1, max.0, max.1),
+                None => "no content".to_owned(),
+            },
+        ];
+        for (name, count) in self.counts.iter() {
+            lines.push(format!("{name}: {count}"));
+        }
+        if !self.unused.is_empty() {
+            lines.push("never used:".to_owned());
+            lines.extend(self.unused.iter().map(|name| format!("  {name}")));
+        }
+        return lines;
+    }
+
+    // tile,count for every tile, unused ones get a 0 and empty cells go last
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("tile,count\n");
+        for (name, count) in self.counts.iter() {
+            csv += &format!("{},{count}\n", csv_field(name));
+        }
+        for name in self.unused.iter() {
+            csv += &format!("{},0\n", csv_field(name));
+        }
+        csv += &format!("(empty),{}\n", self.empty);
+        return csv;
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let mut json_object = object! {
+            rows: self.size.0,
+            cols: self.size.1,
+            empty: self.empty,
+            bounds: null,
+            tiles: {},
+            unused: self.unused.clone(),
+        };
+
+        if let Some((min, max)) = self.bounds {
+            json_object["bounds"] = object! {
+                min: format!("({},{})", min.0, min.1),
+                max: format!("({},{})", max.0, max.1),
+            };
+        }
+        for (name, count) in self.counts.iter() {
+            json_object["tiles"][name.as_str()] = (*count).into();
+        }
+
+        return json_object;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_map::*;
+
+    fn icons() -> MyIconServer<()> {
+        MyIconServer::new(["#", ".", "g", "k"].iter().map(|name| (name.to_string(), ())).collect())
+    }
+
+    #[test]
+    fn counts_tiles_empty_cells_and_bounds() {
+        let grid = TileGrid::from_rows(&[
+            "    ",
+            " ## ",
+            " .g ",
+        ]);
+        let stats = tile_stats(&grid, &icons());
+
+        assert_eq!(stats.size, (3, 4));
+        assert_eq!(stats.counts, BTreeMap::from([("#".to_owned(), 2), (".".to_owned(), 1), ("g".to_owned(), 1)]));
+        assert_eq!(stats.empty, 8);
+        assert_eq!(stats.bounds, Some(((1, 1), (2, 2))));
+        assert_eq!(stats.unused, vec!["k".to_owned()]);
+    }
+
+    #[test]
+    fn empty_grid_has_no_bounds() {
+        let stats = tile_stats(&TileGrid::new(2, 2), &icons());
+        assert!(stats.counts.is_empty());
+        assert_eq!(stats.empty, 4);
+        assert_eq!(stats.bounds, None);
+        assert_eq!(stats.unused.len(), 4);
+        assert!(stats.lines().contains(&"no content".to_owned()));
+    }
+
+    #[test]
+    fn each_layer_is_counted_on_its_own() {
+        let mut map: TileMap<String> = TileMap::new(2, 3, &DEFAULT_LAYER_NAMES);
+        *map.layer_grid_mut(0) = TileGrid::from_rows(&["...", "..."]);
+        *map.layer_grid_mut(1) = TileGrid::from_rows(&["  k", "g  "]);
+
+        let ground = tile_stats(map.active_grid(), &icons());
+        assert_eq!(ground.counts, BTreeMap::from([(".".to_owned(), 6)]));
+        assert_eq!(ground.empty, 0);
+
+        map.set_active_layer_by_name(DEFAULT_LAYER_NAMES[1]);
+        let items = tile_stats(map.active_grid(), &icons());
+        assert_eq!(items.counts, BTreeMap::from([("g".to_owned(), 1), ("k".to_owned(), 1)]));
+        assert_eq!(items.empty, 4);
+        assert_eq!(items.bounds, Some(((0, 0), (2, 1))));
+        assert_eq!(items.unused, vec!["#".to_owned(), ".".to_owned()]);
+
+        map.set_active_layer_by_name(DEFAULT_LAYER_NAMES[2]);
+        assert_eq!(tile_stats(map.active_grid(), &icons()).empty, 6);
+    }
+
+    #[test]
+    fn csv_quotes_names_and_ends_with_empty() {
+        let mut grid = TileGrid::new(1, 3);
+        grid.set((0, 0), Some("rock, big".to_owned()));
+        grid.set((1, 0), Some("#".to_owned()));
+        let csv = tile_stats(&grid, &icons()).to_csv();
+
+        assert_eq!(csv, "tile,count\n#,1\n\"rock, big\",1\n.,0\ng,0\nk,0\n(empty),1\n");
+    }
+
+    #[test]
+    fn json_has_counts_and_bounds() {
+        let grid = TileGrid::from_rows(&[" #", "##"]);
+        let json = tile_stats(&grid, &icons()).to_json();
+
+        assert_eq!(json["rows"], 2);
+        assert_eq!(json["cols"], 2);
+        assert_eq!(json["empty"], 1);
+        assert_eq!(json["tiles"]["#"], 3);
+        assert_eq!(json["bounds"]["min"], "(0,0)");
+        assert_eq!(json["bounds"]["max"], "(1,1)");
+        assert_eq!(json["unused"].len(), 3);
+    }
+}
@@ -0,0 +1,356 @@
+// what shape the cells are. grids always store cells as (x, y) rows and cols,
+// hex maps just push every other row (or col) over by half a cell.
+// the math is from https://www.redblobgames.com/grids/hexagons/
+
+use json::{object, JsonValue};
+
+use crate::tile_grid::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexOrientation {
+    // a point at the top, rows are pushed over
+    Pointy,
+    // flat on top, cols are pushed over
+    Flat,
+}
+
+// which rows (or cols) get pushed over by half a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexOffset {
+    Odd,
+    Even,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexLayout {
+    pub orientation: HexOrientation,
+    pub offset: HexOffset,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Topology {
+    #[default]
+    Square,
+    Hex(HexLayout),
+}
+
+// axial directions, clockwise from the right
+const AXIAL_DIRECTIONS: [(i64, i64); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+
+impl HexLayout {
+    // all four, in the order they get cycled through
+    pub const ALL: [HexLayout; 4] = [
+        HexLayout { orientation: HexOrientation::Pointy, offset: HexOffset::Odd  },
+        HexLayout { orientation: HexOrientation::Pointy, offset: HexOffset::Even },
+        HexLayout { orientation: HexOrientation::Flat,   offset: HexOffset::Odd  },
+        HexLayout { orientation: HexOrientation::Flat,   offset: HexOffset::Even },
+    ];
+
+    // if this row (pointy) or col (flat) is the pushed over one
+    fn is_shifted(&self, line: i64) -> bool {
+        match self.offset {
+            HexOffset::Odd  => line & 1 == 1,
+            HexOffset::Even => line & 1 == 0,
+        }
+    }
+
+    // grid (x, y) to axial (q, r). works off the grid too
+    pub fn axial_of(&self, pos: (i64, i64)) -> (i64, i64) {
+        let (x, y) = pos;
+        match (self.orientation, self.offset) {
+            (HexOrientation::Pointy, HexOffset::Odd ) => (x - (y - (y & 1)) / 2, y),
+            (HexOrientation::Pointy, HexOffset::Even) => (x - (y + (y & 1)) / 2, y),
+            (HexOrientation::Flat,   HexOffset::Odd ) => (x, y - (x - (x & 1)) / 2),
+            (HexOrientation::Flat,   HexOffset::Even) => (x, y - (x + (x & 1)) / 2),
+        }
+    }
+
+    pub fn cell_of_axial(&self, axial: (i64, i64)) -> (i64, i64) {
+        let (q, r) = axial;
+        match (self.orientation, self.offset) {
+            (HexOrientation::Pointy, HexOffset::Odd ) => (q + (r - (r & 1)) / 2, r),
+            (HexOrientation::Pointy, HexOffset::Even) => (q + (r + (r & 1)) / 2, r),
+            (HexOrientation::Flat,   HexOffset::Odd ) => (q, r + (q - (q & 1)) / 2),
+            (HexOrientation::Flat,   HexOffset::Even) => (q, r + (q + (q & 1)) / 2),
+        }
+    }
+
+    // the six cells around pos, might be off the grid
+    pub fn neighbors(&self, pos: (i64, i64)) -> [(i64, i64); 6] {
+        let (q, r) = self.axial_of(pos);
+        AXIAL_DIRECTIONS.map(|(dq, dr)| self.cell_of_axial((q + dq, r + dr)))
+    }
+
+    // the fewest steps between two cells, going through neighbours
+    pub fn distance(&self, a: (i64, i64), b: (i64, i64)) -> i64 {
+        let (aq, ar) = self.axial_of(a);
+        let (bq, br) = self.axial_of(b);
+        let (dq, dr) = (aq - bq, ar - br);
+        (dq.abs() + dr.abs() + (dq + dr).abs()) / 2
+    }
+
+    // middle to corner. spacing is how far apart the middles of two neighbours are
+    pub fn radius(spacing: f32) -> f32 {
+        spacing / 3.0_f32.sqrt()
+    }
+
+    // the middle of a cell, from the top left of the grid. the first cell's corners touch the edges
+    pub fn center(&self, pos: (usize, usize), spacing: f32) -> (f32, f32) {
+        let radius = HexLayout::radius(spacing);
+        let (x, y) = (pos.0 as f32, pos.1 as f32);
+
+        match self.orientation {
+            HexOrientation::Pointy => {
+                let push = if self.is_shifted(pos.1 as i64) { 0.5 } else { 0.0 };
+                (spacing * (x + push) + spacing / 2.0, radius + y * radius * 1.5)
+            }
+            HexOrientation::Flat => {
+                let push = if self.is_shifted(pos.0 as i64) { 0.5 } else { 0.0 };
+                (radius + x * radius * 1.5, spacing * (y + push) + spacing / 2.0)
+            }
+        }
+    }
+
+    // which cell a point is in, from the top left of the grid like center. might be off the grid
+    pub fn cell_at(&self, point: (f32, f32), spacing: f32) -> (i64, i64) {
+        let radius = HexLayout::radius(spacing);
+        let sqrt3 = 3.0_f32.sqrt();
+
+        // undo the margin center puts on, so cell (0, 0) in axial is at the origin
+        let (px, py) = match self.orientation {
+            HexOrientation::Pointy => (point.0 - spacing / 2.0, point.1 - radius),
+            HexOrientation::Flat   => (point.0 - radius, point.1 - spacing / 2.0),
+        };
+        // and the push on row/col 0, cell (0, 0) is (0, 0) in axial for every layout
+        let zero_push = if self.is_shifted(0) { spacing / 2.0 } else { 0.0 };
+
+        let (q, r) = match self.orientation {
+            HexOrientation::Pointy => {
+                let px = px - zero_push;
+                ((sqrt3 / 3.0 * px - py / 3.0) / radius, (2.0 / 3.0 * py) / radius)
+            }
+            HexOrientation::Flat => {
+                let py = py - zero_push;
+                ((2.0 / 3.0 * px) / radius, (-px / 3.0 + sqrt3 / 3.0 * py) / radius)
+            }
+        };
+
+        return self.cell_of_axial(round_axial(q, r));
+    }
+
+    // for raylib's draw_poly, which puts the first corner straight out to the right
+    pub fn rotation(&self) -> f32 {
+        match self.orientation {
+            HexOrientation::Pointy => 30.0,
+            HexOrientation::Flat   => 0.0,
+        }
+    }
+
+    // Returns (width, height) of the whole grid in pixels
+    pub fn grid_size(&self, size: (usize, usize), spacing: f32) -> (f32, f32) {
+        let (rows, cols) = (size.0 as f32, size.1 as f32);
+        let radius = HexLayout::radius(spacing);
+
+        match self.orientation {
+            HexOrientation::Pointy => (spacing * (cols + 0.5), radius * 2.0 + (rows - 1.0).max(0.0) * radius * 1.5),
+            HexOrientation::Flat   => (radius * 2.0 + (cols - 1.0).max(0.0) * radius * 1.5, spacing * (rows + 0.5)),
+        }
+    }
+
+    // adding a row on top (or a col on the left for flat) moves every row down one,
+    // so the other rows have to be the pushed over ones for the map to keep its shape
+    pub fn shifted_by(&self, edges: EdgeDeltas) -> HexLayout {
+        let moved = match self.orientation {
+            HexOrientation::Pointy => edges.top,
+            HexOrientation::Flat   => edges.left,
+        };
+        if moved % 2 == 0 { return *self; }
+
+        let offset = match self.offset {
+            HexOffset::Odd  => HexOffset::Even,
+            HexOffset::Even => HexOffset::Odd,
+        };
+        return HexLayout { offset, ..*self };
+    }
+
+    pub fn name(&self) -> &'static str {
+        match (self.orientation, self.offset) {
+            (HexOrientation::Pointy, HexOffset::Odd ) => "Hex (pointy, odd)",
+            (HexOrientation::Pointy, HexOffset::Even) => "Hex (pointy, even)",
+            (HexOrientation::Flat,   HexOffset::Odd ) => "Hex (flat, odd)",
+            (HexOrientation::Flat,   HexOffset::Even) => "Hex (flat, even)",
+        }
+    }
+}
+
+// the nearest whole cell to a point between cells. rounds all three cube coords,
+// then fixes up the one that moved the most so they still add up to 0
+fn round_axial(q: f32, r: f32) -> (i64, i64) {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    return (rq as i64, rr as i64);
+}
+
+impl Topology {
+    // Square, Hex (pointy, odd), ... Hex (flat, even) and back around
+    pub fn next(&self) -> Topology {
+        match self {
+            Topology::Square => Topology::Hex(HexLayout::ALL[0]),
+            Topology::Hex(layout) => {
+                let index = HexLayout::ALL.iter().position(|other| other == layout).expect("Layout is in ALL");
+                HexLayout::ALL.get(index + 1).map_or(Topology::Square, |next| Topology::Hex(*next))
+            }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Topology::Square => "Square",
+            Topology::Hex(layout) => layout.name(),
+        }
+    }
+
+    // hex cells only have the one kind of neighbour, so 4 vs 8 only matters on squares
+    pub fn connectivity(&self, square: Connectivity) -> Connectivity {
+        match self {
+            Topology::Square => square,
+            Topology::Hex(layout) => Connectivity::Hex(*layout),
+        }
+    }
+}
+
+impl ToAndFromJsonValue for Topology {
+    fn to_json(&self) -> JsonValue {
+        match self {
+            Topology::Square => object! { kind: "square" },
+            Topology::Hex(layout) => object! {
+                kind: "hex",
+                orientation: match layout.orientation {
+                    HexOrientation::Pointy => "pointy",
+                    HexOrientation::Flat   => "flat",
+                },
+                offset: match layout.offset {
+                    HexOffset::Odd  => "odd",
+                    HexOffset::Even => "even",
+                },
+            },
+        }
+    }
+
+    fn from_json(source: &JsonValue) -> Option<Self> {
+        match source["kind"].as_str()? {
+            "square" => Some(Topology::Square),
+            "hex" => {
+                let orientation = match source["orientation"].as_str()? {
+                    "pointy" => HexOrientation::Pointy,
+                    "flat"   => HexOrientation::Flat,
+                    _ => return None,
+                };
+                let offset = match source["offset"].as_str()? {
+                    "odd"  => HexOffset::Odd,
+                    "even" => HexOffset::Even,
+                    _ => return None,
+                };
+                Some(Topology::Hex(HexLayout { orientation, offset }))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTY_ODD: HexLayout = HexLayout { orientation: HexOrientation::Pointy, offset: HexOffset::Odd };
+    const FLAT_ODD: HexLayout = HexLayout { orientation: HexOrientation::Flat, offset: HexOffset::Odd };
+
+    fn sorted(mut cells: [(i64, i64); 6]) -> [(i64, i64); 6] {
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn axial_round_trips_for_every_layout() {
+        for layout in HexLayout::ALL {
+            for y in -4..5 {
+                for x in -4..5 {
+                    assert_eq!(layout.cell_of_axial(layout.axial_of((x, y))), (x, y), "{}", layout.name());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn odd_rows_are_pushed_right() {
+        // row 1 is pushed over, so the cells above and below it are x and x + 1
+        assert_eq!(sorted(POINTY_ODD.neighbors((1, 1))), sorted([(0, 1), (2, 1), (1, 0), (2, 0), (1, 2), (2, 2)]));
+        // and row 2 isn't, so they're x - 1 and x
+        assert_eq!(sorted(POINTY_ODD.neighbors((1, 2))), sorted([(0, 2), (2, 2), (0, 1), (1, 1), (0, 3), (1, 3)]));
+    }
+
+    #[test]
+    fn odd_cols_are_pushed_down() {
+        assert_eq!(sorted(FLAT_ODD.neighbors((1, 1))), sorted([(1, 0), (1, 2), (0, 1), (0, 2), (2, 1), (2, 2)]));
+        assert_eq!(sorted(FLAT_ODD.neighbors((2, 1))), sorted([(2, 0), (2, 2), (1, 0), (1, 1), (3, 0), (3, 1)]));
+    }
+
+    #[test]
+    fn neighbors_go_both_ways() {
+        for layout in HexLayout::ALL {
+            for pos in [(0, 0), (1, 0), (0, 1), (3, 4), (4, 3)] {
+                for next in layout.neighbors(pos) {
+                    assert!(layout.neighbors(next).contains(&pos), "{} {:?} {:?}", layout.name(), pos, next);
+                    assert_eq!(layout.distance(pos, next), 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn distance_counts_steps() {
+        assert_eq!(POINTY_ODD.distance((0, 0), (0, 0)), 0);
+        assert_eq!(POINTY_ODD.distance((0, 0), (3, 0)), 3);
+        // straight down zig zags, so two rows down is still two steps
+        assert_eq!(POINTY_ODD.distance((0, 0), (0, 2)), 2);
+        assert_eq!(POINTY_ODD.distance((0, 0), (2, 4)), 4);
+        assert_eq!(FLAT_ODD.distance((0, 0), (4, 0)), 4);
+        assert_eq!(FLAT_ODD.distance((0, 0), (0, 3)), 3);
+    }
+
+    #[test]
+    fn cell_at_finds_the_cell_up_to_its_edges() {
+        let spacing = 20.0;
+        for layout in HexLayout::ALL {
+            for pos in [(0, 0), (1, 0), (0, 1), (2, 3), (3, 2)] {
+                let center = layout.center(pos, spacing);
+                assert_eq!(layout.cell_at(center, spacing), (pos.0 as i64, pos.1 as i64));
+
+                // the edge between two neighbours is halfway between their middles
+                for next in layout.neighbors((pos.0 as i64, pos.1 as i64)) {
+                    if next.0 < 0 || next.1 < 0 { continue; }
+                    let other = layout.center((next.0 as usize, next.1 as usize), spacing);
+                    let toward = |amount: f32| (center.0 + (other.0 - center.0) * amount, center.1 + (other.1 - center.1) * amount);
+
+                    assert_eq!(layout.cell_at(toward(0.45), spacing), (pos.0 as i64, pos.1 as i64), "{}", layout.name());
+                    assert_eq!(layout.cell_at(toward(0.55), spacing), next, "{}", layout.name());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn topology_json_round_trips() {
+        for topology in [Topology::Square, Topology::Hex(POINTY_ODD), Topology::Hex(FLAT_ODD)] {
+            assert_eq!(Topology::from_json(&topology.to_json()), Some(topology));
+        }
+        assert_eq!(Topology::from_json(&object! { kind: "hex", orientation: "sideways", offset: "odd" }), None);
+    }
+}
@@ -0,0 +1,372 @@
+// checking that a level can actually be walked through, before it ships.
+// a tile blocks if its definition has "walkable": false or "blocking": true,
+// and cells with nothing on any layer are holes
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+use crate::tile_grid::*;
+use crate::tile_map::*;
+use crate::tile_definition::*;
+use crate::map_merge::read_json_file;
+use crate::floor_stack::FloorStack;
+
+// objects with these names mark where the player starts and where they have to get to
+pub const START_OBJECT_NAME : &str = "start";
+pub const GOAL_OBJECT_NAME  : &str = "goal";
+
+pub fn is_blocking(definition: Option<&TileDefinition>) -> bool {
+    let Some(definition) = definition else { return false; };
+    definition.properties.get("walkable") == Some(&TileProperty::Bool(false))
+        || definition.properties.get("blocking") == Some(&TileProperty::Bool(true))
+}
+
+// Some(true) for cells you can stand on. every layer counts, so a wall on the items layer
+// blocks the floor under it
+pub fn walkable_grid<'a>(map: &TileMap<String>, definition_of: impl Fn(&str) -> Option<&'a TileDefinition>) -> TileGrid<bool> {
+    let (rows, cols) = map.size();
+    let mut grid = TileGrid::new(rows, cols);
+
+    for y in 0..rows {
+        for x in 0..cols {
+            let stack = map.get_stack((x, y));
+            let walkable = !stack.is_empty() && stack.iter().all(|(name, _)| !is_blocking(definition_of(name)));
+            grid.set((x, y), Some(walkable));
+        }
+    }
+    return grid;
+}
+
+fn is_walkable(grid: &TileGrid<bool>, pos: (usize, usize)) -> bool {
+    grid.get(pos) == &Some(true)
+}
+
+// groups of walkable cells that can't get to each other, biggest first
+pub fn walkable_areas(grid: &TileGrid<bool>, connectivity: Connectivity) -> Vec<Vec<(usize, usize)>> {
+    let mut seen = vec![false; grid.rows * grid.cols];
+    let mut areas = vec![];
+
+    for y in 0..grid.rows {
+        for x in 0..grid.cols {
+            if seen[pos_to_index((x, y), grid.size())] || !is_walkable(grid, (x, y)) { continue; }
+
+            let mut area = vec![];
+            let mut stack = vec![(x, y)];
+            seen[pos_to_index((x, y), grid.size())] = true;
+
+            while let Some(pos) = stack.pop() {
+                area.push(pos);
+                for next in grid.neighbors(pos, connectivity) {
+                    let index = pos_to_index(next, grid.size());
+                    if seen[index] || !is_walkable(grid, next) { continue; }
+                    seen[index] = true;
+                    stack.push(next);
+                }
+            }
+
+            area.sort_by_key(|pos| (pos.1, pos.0));
+            areas.push(area);
+        }
+    }
+
+    // stable, so same sized ones stay in reading order
+    areas.sort_by_key(|area| Reverse(area.len()));
+    return areas;
+}
+
+// the fewest steps from a to b if nothing was in the way, every step costs 1
+fn step_distance(a: (usize, usize), b: (usize, usize), connectivity: Connectivity) -> usize {
+    let dx = a.0.abs_diff(b.0);
+    let dy = a.1.abs_diff(b.1);
+
+    match connectivity {
+        Connectivity::Four  => dx + dy,
+        Connectivity::Eight => dx.max(dy),
+        Connectivity::Hex(layout) => layout.distance((a.0 as i64, a.1 as i64), (b.0 as i64, b.1 as i64)) as usize,
+    }
+}
+
+// A*, Returns the cells from start to goal (both included), or None if there's no way through
+pub fn shortest_path(grid: &TileGrid<bool>, start: (usize, usize), goal: (usize, usize), connectivity: Connectivity) -> Option<Vec<(usize, usize)>> {
+    if !is_walkable(grid, start) || !is_walkable(grid, goal) { return None; }
+
+    let mut came_from: BTreeMap<(usize, usize), (usize, usize)> = BTreeMap::new();
+    let mut best = BTreeMap::from([(start, 0)]);
+    // ties fall back to comparing the cells, so it's the same path every run
+    let mut open = BinaryHeap::from([Reverse((step_distance(start, goal, connectivity), 0, start))]);
+
+    while let Some(Reverse((_, steps, pos))) = open.pop() {
+        if pos == goal {
+            let mut path = vec![goal];
+            while let Some(previous) = came_from.get(path.last().unwrap()) {
+                path.push(*previous);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        // already got here a shorter way
+        if best.get(&pos).is_some_and(|best_steps| *best_steps < steps) { continue; }
+
+        for next in grid.neighbors(pos, connectivity) {
+            if !is_walkable(grid, next) { continue; }
+            let next_steps = steps + 1;
+            if best.get(&next).is_some_and(|best_steps| *best_steps <= next_steps) { continue; }
+
+            best.insert(next, next_steps);
+            came_from.insert(next, pos);
+            open.push(Reverse((next_steps + step_distance(next, goal, connectivity), next_steps, next)));
+        }
+    }
+
+    return None;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathCheck {
+    pub start: (usize, usize),
+    pub goal: (usize, usize),
+    pub path: Option<Vec<(usize, usize)>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalkReport {
+    pub walkable: TileGrid<bool>,
+    pub areas: Vec<Vec<(usize, usize)>>,
+    // every start to every goal
+    pub paths: Vec<PathCheck>,
+}
+
+// the cell an object is sitting in. objects can go right up to the far edge, which is still the last cell
+fn object_cell(position: (f32, f32), size: (usize, usize)) -> (usize, usize) {
+    let (rows, cols) = size;
+    let x = (position.0.max(0.0) as usize).min(cols.saturating_sub(1));
+    let y = (position.1.max(0.0) as usize).min(rows.saturating_sub(1));
+    return (x, y);
+}
+
+// square maps only go up, down, left and right, you don't get to squeeze between two walls
+pub fn check_floor<'a>(map: &TileMap<String>, definition_of: impl Fn(&str) -> Option<&'a TileDefinition>) -> WalkReport {
+    let connectivity = map.topology.connectivity(Connectivity::Four);
+    let walkable = walkable_grid(map, definition_of);
+    let areas = walkable_areas(&walkable, connectivity);
+
+    let marked = |name: &str| -> Vec<(usize, usize)> {
+        map.objects
+            .iter()
+            .filter(|map_object| map_object.name == name)
+            .map(|map_object| object_cell(map_object.position, map.size()))
+            .collect()
+    };
+
+    let mut paths = vec![];
+    for start in marked(START_OBJECT_NAME) {
+        for goal in marked(GOAL_OBJECT_NAME) {
+            paths.push(PathCheck { start, goal, path: shortest_path(&walkable, start, goal, connectivity) });
+        }
+    }
+
+    WalkReport { walkable, areas, paths }
+}
+
+impl WalkReport {
+    // more than one area means there's somewhere you can't get to
+    pub fn is_ok(&self) -> bool {
+        self.areas.len() <= 1 && self.paths.iter().all(|check| check.path.is_some())
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+
+        match self.areas.len() {
+            0 => lines.push("nothing walkable".to_owned()),
+            1 => lines.push(format!("all {} walkable cells connected", self.areas[0].len())),
+            count => {
+                lines.push(format!("{count} walkable areas that can't reach each other"));
+                for area in self.areas.iter() {
+                    lines.push(format!("  {} cells from ({},{})", area.len(), area[0].0, area[0].1));
+                }
+            }
+        }
+
+        if self.paths.is_empty() {
+            lines.push(format!("no \"{START_OBJECT_NAME}\" and \"{GOAL_OBJECT_NAME}\" objects to path between"));
+        }
+        for check in self.paths.iter() {
+            let (start, goal) = (check.start, check.goal);
+            lines.push(match check.path.as_ref() {
+                Some(path) => format!("({},{}) to ({},{}): {} steps", start.0, start.1, goal.0, goal.1, path.len() - 1),
+                None if !is_walkable(&self.walkable, start) => format!("({},{}) to ({},{}): UNREACHABLE, the start is blocked", start.0, start.1, goal.0, goal.1),
+                None if !is_walkable(&self.walkable, goal)  => format!("({},{}) to ({},{}): UNREACHABLE, the goal is blocked", start.0, start.1, goal.0, goal.1),
+                None => format!("({},{}) to ({},{}): UNREACHABLE", start.0, start.1, goal.0, goal.1),
+            });
+        }
+
+        return lines;
+    }
+}
+
+// `tile_editor validate <map>...`, the tile properties come from the palette saved in each map.
+// Returns the exit code, 0 if every floor of every map passes and 1 if not, or if a map couldn't be read
+pub fn run_validate(paths: &[&str]) -> i32 {
+    let mut failed = false;
+
+    for path in paths {
+        // a map that can't be checked fails, a typo in the path shouldn't pass the build
+        let Some(source) = read_json_file(path).filter(|source| !source.is_null()) else {
+            eprintln!("{path}: missing or not valid json");
+            failed = true;
+            continue;
+        };
+        let Some(floors) = FloorStack::<String>::from_json(&source) else {
+            eprintln!("{path}: not a valid map");
+            failed = true;
+            continue;
+        };
+
+        let definitions: Option<BTreeMap<String, TileDefinition>> = source["palette"]
+            .entries()
+            .map(|(name, value)| Some((name.to_owned(), TileDefinition::from_json(value)?)))
+            .collect();
+        let Some(definitions) = definitions else {
+            eprintln!("{path}: has a bad tile definition in its palette");
+            failed = true;
+            continue;
+        };
+
+        for (index, floor) in floors.floors().iter().enumerate() {
+            let report = check_floor(floor, |name| definitions.get(name));
+            let status = if report.is_ok() { "ok" } else { "FAILED" };
+            println!("{path} floor {}: {status}", index + 1);
+            for line in report.lines() {
+                println!("  {line}");
+            }
+            failed |= !report.is_ok();
+        }
+    }
+
+    return if failed { 1 } else { 0 };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_object::MapObject;
+
+    // # is a wall, . is floor, space is a hole, S and G are the start and goal on floor
+    fn map(rows: &[&str]) -> TileMap<String> {
+        let mut map = TileMap::new(rows.len(), rows[0].len(), &DEFAULT_LAYER_NAMES);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let tile = match c {
+                    '#' => "wall",
+                    ' ' => continue,
+                    _ => "floor",
+                };
+                map.layer_grid_mut(0).set((x, y), Some(tile.to_owned()));
+
+                let center = (x as f32 + 0.5, y as f32 + 0.5);
+                if c == 'S' { map.objects.push(MapObject::new(START_OBJECT_NAME, "start.png", center)); }
+                if c == 'G' { map.objects.push(MapObject::new(GOAL_OBJECT_NAME, "goal.png", center)); }
+            }
+        }
+        map
+    }
+
+    fn wall() -> TileDefinition {
+        let mut definition = TileDefinition::default();
+        definition.properties.insert("walkable".to_owned(), TileProperty::Bool(false));
+        definition
+    }
+
+    fn check(rows: &[&str]) -> WalkReport {
+        let wall = wall();
+        check_floor(&map(rows), |name| (name == "wall").then_some(&wall))
+    }
+
+    #[test]
+    fn finds_the_shortest_way_round() {
+        let report = check(&[
+            "S..#....",
+            "##.#.##.",
+            "...#..#G",
+            ".####...",
+            "........",
+        ]);
+        assert!(report.is_ok(), "{:?}", report.lines());
+        let path = report.paths[0].path.as_ref().unwrap();
+        assert_eq!(path.len() - 1, 17);
+        assert_eq!((path[0], path[path.len() - 1]), ((0, 0), (7, 2)));
+    }
+
+    #[test]
+    fn every_step_is_to_a_neighbour() {
+        // a wall down the middle with a gap at the bottom
+        let mut grid = TileGrid::new(5, 5);
+        for i in 0..25 {
+            let (x, y) = index_to_pos(i, grid.size());
+            grid.set((x, y), Some(x != 2 || y == 4));
+        }
+
+        for (connectivity, steps) in [(Connectivity::Four, 12), (Connectivity::Eight, 8)] {
+            let path = shortest_path(&grid, (0, 0), (4, 0), connectivity).unwrap();
+            assert_eq!(path.len() - 1, steps);
+            for step in path.windows(2) {
+                assert!(grid.neighbors(step[0], connectivity).contains(&step[1]));
+                assert_eq!(grid.get(step[1]), &Some(true));
+            }
+        }
+    }
+
+    #[test]
+    fn walled_off_goals_are_unreachable() {
+        let report = check(&["S.#.G"]);
+        assert!(!report.is_ok());
+        assert_eq!(report.areas.len(), 2);
+        assert_eq!(report.paths[0].path, None);
+        assert!(report.lines().iter().any(|line| line.ends_with("UNREACHABLE")));
+    }
+
+    #[test]
+    fn no_squeezing_between_diagonal_walls() {
+        let report = check(&["S#", "#G"]);
+        assert_eq!(report.paths[0].path, None);
+    }
+
+    #[test]
+    fn blocked_or_missing_ends_are_unreachable() {
+        let mut grid = TileGrid::new(1, 3);
+        grid.set((0, 0), Some(true));
+        grid.set((1, 0), Some(true));
+        grid.set((2, 0), Some(false));
+        assert_eq!(shortest_path(&grid, (0, 0), (2, 0), Connectivity::Four), None);
+        assert_eq!(shortest_path(&grid, (2, 0), (0, 0), Connectivity::Four), None);
+        assert_eq!(shortest_path(&grid, (0, 0), (0, 0), Connectivity::Four), Some(vec![(0, 0)]));
+
+        // holes block too
+        let report = check(&["S G"]);
+        assert_eq!(report.paths[0].path, None);
+    }
+
+    #[test]
+    fn maps_that_cant_be_read_fail_validation() {
+        let dir = std::env::temp_dir().join(format!("tile_editor_validate_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_owned();
+
+        let mut good = map(&["S.G"]).to_json();
+        std::fs::write(path("good.json"), good.dump()).unwrap();
+        std::fs::write(path("not json.json"), "{ oops").unwrap();
+        std::fs::write(path("palette.json"), r#"{ "version": "1.0", "tiles": {} }"#).unwrap();
+        good["palette"] = json::object! { "#": { "properties": { "walkable": { "type": "bool", "value": 3 } } } };
+        std::fs::write(path("bad palette.json"), good.dump()).unwrap();
+
+        let results: Vec<i32> = ["good.json", "missing.json", "not json.json", "palette.json", "bad palette.json"]
+            .iter()
+            .map(|name| run_validate(&[&path(name)]))
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(results, vec![0, 1, 1, 1, 1]);
+    }
+}
@@ -0,0 +1,265 @@
+// wave function collapse, the simple tiled kind. it learns which tiles sit next to which
+// from hand made maps, then fills a grid so every pair of neighbours is one it has seen.
+// empty cells in the examples don't teach it anything
+
+use crate::tile_grid::*;
+use crate::random::SeededRng;
+
+// right, down, left, up. the opposite of a direction is 2 away
+const DIRECTIONS: [(i64, i64); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+fn opposite(direction: usize) -> usize {
+    (direction + 2) % DIRECTIONS.len()
+}
+
+fn step(pos: (usize, usize), direction: usize, size: (usize, usize)) -> Option<(usize, usize)> {
+    let (dx, dy) = DIRECTIONS[direction];
+    let (x, y) = (pos.0 as i64 + dx, pos.1 as i64 + dy);
+    if x < 0 || y < 0 || x as usize >= size.1 || y as usize >= size.0 { return None; }
+    return Some((x as usize, y as usize));
+}
+
+#[derive(Debug, Clone)]
+pub struct WfcModel<T> {
+    // an item and the way it faces, a turned wall is a different tile to a straight one
+    tiles: Vec<(T, Orientation)>,
+    // how many times each one showed up, common tiles get picked more
+    weights: Vec<f64>,
+    // allowed[direction][a][b], b can sit that way from a
+    allowed: [Vec<Vec<bool>>; 4],
+}
+
+// where it got stuck, nothing could go in this cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WfcContradiction {
+    pub pos: (usize, usize),
+    pub seed: u64,
+}
+
+impl std::fmt::Display for WfcContradiction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "contradiction at ({},{}) with seed {}", self.pos.0, self.pos.1, self.seed)
+    }
+}
+
+impl<T> WfcModel<T>
+where
+    T: Clone + PartialEq,
+{
+    pub fn learn(examples: &[&TileGrid<T>]) -> Self {
+        let mut model = WfcModel { tiles: vec![], weights: vec![], allowed: Default::default() };
+
+        for grid in examples {
+            for y in 0..grid.rows {
+                for x in 0..grid.cols {
+                    let Some(tile) = model.tile_at(grid, (x, y)) else { continue; };
+                    model.weights[tile] += 1.0;
+
+                    for direction in 0..DIRECTIONS.len() {
+                        let Some(next) = step((x, y), direction, grid.size()) else { continue; };
+                        let Some(next_tile) = model.tile_at(grid, next) else { continue; };
+                        model.allowed[direction][tile][next_tile] = true;
+                        model.allowed[opposite(direction)][next_tile][tile] = true;
+                    }
+                }
+            }
+        }
+
+        return model;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    fn find(&self, item: &T, orientation: Orientation) -> Option<usize> {
+        self.tiles.iter().position(|(other, other_orientation)| other == item && *other_orientation == orientation)
+    }
+
+    // adds it if it's new, every table grows by one
+    fn tile_at(&mut self, grid: &TileGrid<T>, pos: (usize, usize)) -> Option<usize> {
+        let item = grid.get(pos).as_ref()?;
+        let orientation = grid.get_orientation(pos);
+        if let Some(index) = self.find(item, orientation) { return Some(index); }
+
+        self.tiles.push((item.clone(), orientation));
+        self.weights.push(0.0);
+        for table in self.allowed.iter_mut() {
+            for row in table.iter_mut() {
+                row.push(false);
+            }
+            table.push(vec![false; self.tiles.len()]);
+        }
+        return Some(self.tiles.len() - 1);
+    }
+
+    // only_empty keeps what's already in target and works around it. tiles in target the
+    // examples never had are left alone and don't limit their neighbours.
+    // the same seed and target always give the same grid
+    pub fn generate(&self, target: &TileGrid<T>, only_empty: bool, seed: u64) -> Result<TileGrid<T>, WfcContradiction> {
+        let size = target.size();
+        let mut rng = SeededRng::new(seed);
+        let contradiction = |pos| WfcContradiction { pos, seed };
+
+        // which tiles each cell could still be, None for cells that are staying how they are
+        let mut wave: Vec<Option<Vec<bool>>> = vec![Some(vec![true; self.tiles.len()]); size.0 * size.1];
+        // kept tiles can't be taken away, whatever is next to them has to give instead
+        let mut fixed = vec![false; size.0 * size.1];
+        let mut changed = vec![];
+
+        if only_empty {
+            for y in 0..size.0 {
+                for x in 0..size.1 {
+                    let Some(item) = target.get((x, y)).as_ref() else { continue; };
+                    let index = pos_to_index((x, y), size);
+                    wave[index] = self.find(item, target.get_orientation((x, y)))
+                        .map(|tile| (0..self.tiles.len()).map(|other| other == tile).collect());
+                    fixed[index] = wave[index].is_some();
+                    if fixed[index] { changed.push((x, y)); }
+                }
+            }
+        }
+        if self.tiles.is_empty() && wave.iter().any(Option::is_some) {
+            return Err(contradiction((0, 0)));
+        }
+        self.propagate(&mut wave, &fixed, changed, size).map_err(contradiction)?;
+
+        // the least sure cell gets decided, then everything around it catches up
+        while let Some(pos) = self.least_sure(&wave, size, &mut rng) {
+            let options = wave[pos_to_index(pos, size)].as_mut().unwrap();
+            let weights: Vec<f64> = self.weights.iter().zip(options.iter()).map(|(weight, open)| if *open { *weight } else { 0.0 }).collect();
+            let pick = rng.weighted(&weights);
+            options.iter_mut().enumerate().for_each(|(tile, open)| *open = tile == pick);
+
+            self.propagate(&mut wave, &fixed, vec![pos], size).map_err(contradiction)?;
+        }
+
+        let mut result = target.clone();
+        for y in 0..size.0 {
+            for x in 0..size.1 {
+                let Some(options) = wave[pos_to_index((x, y), size)].as_ref() else { continue; };
+                let tile = options.iter().position(|open| *open).expect("Every cell was decided");
+                let (item, orientation) = self.tiles[tile].clone();
+                result.set_oriented((x, y), Some(item), orientation);
+            }
+        }
+        return Ok(result);
+    }
+
+    // takes tiles off the neighbours that can't sit next to anything left in a changed cell.
+    // Returns the cell that ran out of tiles, or that can't fit next to a kept one, if there was one
+    fn propagate(&self, wave: &mut [Option<Vec<bool>>], fixed: &[bool], mut changed: Vec<(usize, usize)>, size: (usize, usize)) -> Result<(), (usize, usize)> {
+        while let Some(pos) = changed.pop() {
+            let Some(options) = wave[pos_to_index(pos, size)].clone() else { continue; };
+
+            for (direction, table) in self.allowed.iter().enumerate() {
+                let Some(next) = step(pos, direction, size) else { continue; };
+                let Some(next_options) = wave[pos_to_index(next, size)].as_mut() else { continue; };
+
+                let mut shrunk = false;
+                for (tile, open) in next_options.iter_mut().enumerate() {
+                    if !*open { continue; }
+                    let supported = options.iter().enumerate().any(|(other, other_open)| *other_open && table[other][tile]);
+                    if !supported {
+                        *open = false;
+                        shrunk = true;
+                    }
+                }
+
+                if shrunk && fixed[pos_to_index(next, size)] { return Err(pos); }
+                if !next_options.contains(&true) { return Err(next); }
+                if shrunk { changed.push(next); }
+            }
+        }
+        return Ok(());
+    }
+
+    // the undecided cell with the fewest likely tiles, ties broken by the seed.
+    // Returns None once every cell is down to one
+    fn least_sure(&self, wave: &[Option<Vec<bool>>], size: (usize, usize), rng: &mut SeededRng) -> Option<(usize, usize)> {
+        let mut best: Vec<(usize, usize)> = vec![];
+        let mut best_entropy = f64::MAX;
+
+        for (index, options) in wave.iter().enumerate() {
+            let Some(options) = options else { continue; };
+            if options.iter().filter(|open| **open).count() <= 1 { continue; }
+
+            // shannon entropy of what's left, weighted by how often each tile showed up
+            let weights: Vec<f64> = self.weights.iter().zip(options.iter()).filter(|(_, open)| **open).map(|(weight, _)| *weight).collect();
+            let total: f64 = weights.iter().sum();
+            let entropy = total.ln() - weights.iter().map(|weight| weight * weight.ln()).sum::<f64>() / total;
+
+            if entropy < best_entropy - 1e-9 {
+                best_entropy = entropy;
+                best.clear();
+            }
+            if entropy < best_entropy + 1e-9 {
+                best.push(index_to_pos(index, size));
+            }
+        }
+
+        if best.is_empty() { return None; }
+        return Some(best[rng.below(best.len())]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a room with walls round the outside, floor in the middle
+    fn room_model() -> WfcModel<String> {
+        WfcModel::learn(&[&TileGrid::from_rows(&[
+            "#####",
+            "#...#",
+            "#...#",
+            "#####",
+        ])])
+    }
+
+    #[test]
+    fn same_seed_same_grid() {
+        let model = room_model();
+        let target = TileGrid::new(8, 12);
+
+        let first = model.generate(&target, false, 42).unwrap();
+        assert_eq!(first.to_rows(), model.generate(&target, false, 42).unwrap().to_rows());
+        assert!(first.to_rows().iter().all(|row| !row.contains(' ')));
+    }
+
+    #[test]
+    fn only_neighbours_it_has_seen() {
+        // a and b only ever sit side by side, a on the left
+        let model = WfcModel::learn(&[&TileGrid::from_rows(&["ab"])]);
+        let result = model.generate(&TileGrid::new(1, 2), false, 7).unwrap();
+        assert_eq!(result.to_rows(), vec!["ab"]);
+    }
+
+    #[test]
+    fn keeps_what_is_already_there() {
+        let model = room_model();
+        let target = TileGrid::from_rows(&[
+            "        ",
+            "   .    ",
+            "        ",
+        ]);
+        for seed in 0..10 {
+            let result = model.generate(&target, true, seed).unwrap();
+            assert_eq!(result.get((3, 1)).as_deref(), Some("."));
+        }
+    }
+
+    #[test]
+    fn contradictions_say_where_and_which_seed() {
+        // a needs a b on its right, and there's no room for one
+        let model = WfcModel::learn(&[&TileGrid::from_rows(&["ab"])]);
+        let error = model.generate(&TileGrid::from_rows(&["a b"]), true, 1).unwrap_err();
+        assert_eq!(error, WfcContradiction { pos: (1, 0), seed: 1 });
+        assert_eq!(error.to_string(), "contradiction at (1,0) with seed 1");
+    }
+
+    #[test]
+    fn nothing_learned_from_empty_examples() {
+        assert!(WfcModel::<String>::learn(&[&TileGrid::new(3, 3)]).is_empty());
+    }
+}